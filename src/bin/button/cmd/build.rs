@@ -18,13 +18,16 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 
 use num_cpus;
 use structopt::StructOpt;
 
-use button::{self, events, Error, ResultExt, Rules};
+use button::{
+    self, events, res::ChecksumAlgorithm, stats::Stats, Error, ResultExt,
+};
 
 use crate::opts::GlobalOpts;
 use crate::paths;
@@ -49,6 +52,59 @@ pub struct Build {
     #[structopt(long = "clean")]
     clean: bool,
 
+    /// The algorithm used to compute checksums of resources. Changing this
+    /// causes everything to be rebuilt.
+    #[structopt(
+        long = "checksum",
+        default_value = "sha256",
+        possible_values = &ChecksumAlgorithm::variants(),
+    )]
+    checksum: ChecksumAlgorithm,
+
+    /// Also writes build events as JSON lines to the given file. Use "-" to
+    /// write them to stdout. Each line is a JSON object with a "timestamp"
+    /// and an "event".
+    #[structopt(long = "events-json", parse(from_os_str))]
+    events_json: Option<PathBuf>,
+
+    /// Writes a profile of the build to the given file in the Chrome Trace
+    /// Event Format. This can be viewed in `chrome://tracing` or Perfetto.
+    /// Use "-" to write it to stdout.
+    #[structopt(long = "trace", parse(from_os_str))]
+    trace: Option<PathBuf>,
+
+    /// Writes a JUnit XML report to the given file where each task is a test
+    /// case. Use "-" to write it to stdout.
+    #[structopt(long = "junit", parse(from_os_str))]
+    junit: Option<PathBuf>,
+
+    /// Writes a self-contained HTML report of the build to the given file.
+    /// Use "-" to write it to stdout.
+    #[structopt(long = "html", parse(from_os_str))]
+    html: Option<PathBuf>,
+
+    /// Publishes build events to the Unix domain socket ".button/events.sock"
+    /// so that other programs (e.g., editors) can follow the build. Any number
+    /// of programs can connect. Each event is prefixed by its length as a
+    /// big-endian 32-bit integer and is encoded in the given format.
+    #[cfg(unix)]
+    #[structopt(
+        long = "publish",
+        possible_values = &events::PublishFormat::variants(),
+    )]
+    publish: Option<events::PublishFormat>,
+
+    /// Writes the output of each task to its own file in ".button/logs". When
+    /// the build finishes, a summary of the failed tasks is printed along with
+    /// the paths to their logs.
+    #[structopt(long = "task-logs")]
+    task_logs: bool,
+
+    /// Used with "--task-logs". The number of lines of output to show for
+    /// each failed task in the summary.
+    #[structopt(long = "summary-lines", default_value = "10")]
+    summary_lines: usize,
+
     /// Watch for changes and build automatically.
     #[structopt(long = "watch")]
     watch: bool,
@@ -65,11 +121,28 @@ pub struct Build {
     /// automatic formatting, etc).
     #[structopt(long = "watch-delay", default_value = "100")]
     watch_delay: usize,
+
+    /// Only builds tasks with this tag and what they depend on. Can be given
+    /// more than once. If neither tags nor targets are specified, builds the
+    /// default tags of the build rules.
+    #[structopt(long = "tag", number_of_values = 1)]
+    tags: Vec<String>,
+
+    /// Doesn't build tasks with this tag unless something else that is built
+    /// depends on them. Can be given more than once.
+    #[structopt(long = "exclude-tag", number_of_values = 1)]
+    exclude_tags: Vec<String>,
+
+    /// The aliases or resources to build. Resources are paths relative to the
+    /// directory of the build rules. If not specified, builds everything.
+    targets: Vec<String>,
 }
 
 impl Build {
     pub fn main(self, _global: &GlobalOpts) -> Result<(), Error> {
-        let rules = paths::rules_or(self.rules)
+        let stdout_taken = self.stdout_taken();
+
+        let rules_path = paths::rules_or(self.rules)
             .context("Failed to find build rules")?;
 
         let threads = if self.threads == 0 {
@@ -78,7 +151,7 @@ impl Build {
             self.threads
         };
 
-        let root = rules.parent().unwrap_or_else(|| Path::new("."));
+        let root = rules_path.parent().unwrap_or_else(|| Path::new("."));
 
         // Ensure the .button directory exists.
         paths::init(&root).context("Failed initializing .button directory")?;
@@ -86,24 +159,124 @@ impl Build {
         let f = fs::File::create(paths::LOG)
             .with_context(|_| format!("Failed to create '{}'", paths::LOG))?;
 
-        // TODO: If stderr is not a TTY, use an event handler that dumps the
-        // full output to a file.
-        let event_handler: Vec<events::AnyHandler> =
-            vec![events::Console::new().into(), events::Binary::new(f).into()];
+        let stats_path = root.join(paths::STATS);
+        let stats = match Stats::from_path(&stats_path) {
+            Ok(stats) => stats,
+            Err(err) => {
+                // Statistics aren't worth failing the build over, but the
+                // history is about to be overwritten, so say so.
+                eprintln!(
+                    "Warning: {}: {}. Starting a new history of build \
+                     statistics.",
+                    err,
+                    err.find_root_cause()
+                );
+                Stats::default()
+            }
+        };
+
+        // Progress bars are only useful in a terminal. Otherwise, print plain
+        // lines of text that are suitable for logs.
+        let console: events::AnyHandler = if atty::is(atty::Stream::Stdout) {
+            events::Console::new().with_stats(stats.clone()).into()
+        } else {
+            let writer: Box<dyn io::Write + Send> = if stdout_taken {
+                Box::new(io::stderr())
+            } else {
+                Box::new(io::stdout())
+            };
+
+            events::Plain::new(writer).into()
+        };
+
+        let mut event_handler: Vec<events::AnyHandler> =
+            vec![console, events::Binary::new(f).into()];
+
+        // Timings from a dry run are meaningless.
+        if !self.dryrun {
+            event_handler
+                .push(events::StatsRecorder::new(stats_path, stats).into());
+        }
+
+        if let Some(path) = &self.events_json {
+            event_handler
+                .push(events::JsonLines::new(output_writer(path)?).into());
+        }
+
+        if let Some(path) = &self.trace {
+            event_handler.push(events::Trace::new(output_writer(path)?).into());
+        }
+
+        if let Some(path) = &self.junit {
+            event_handler.push(events::JUnit::new(output_writer(path)?).into());
+        }
+
+        if let Some(path) = &self.html {
+            event_handler.push(events::Html::new(output_writer(path)?).into());
+        }
+
+        #[cfg(unix)]
+        {
+            if let Some(format) = self.publish {
+                let path = root.join(paths::EVENTS_SOCKET);
+                let publisher = events::Publisher::bind(&path, format)
+                    .with_context(|_| {
+                        format!("Failed to create socket '{}'", path.display())
+                    })?;
+                event_handler.push(publisher.into());
+            }
+        }
+
+        if self.task_logs {
+            event_handler.push(
+                events::TaskLogs::new(
+                    root.join(paths::LOGS),
+                    self.summary_lines,
+                )
+                .into(),
+            );
+        }
+
         let (sender, receiver) = mpsc::channel();
         let _event_thread = events::EventThread::new(event_handler, receiver);
 
         let state_path = root.join(paths::STATE);
-        let build = button::Build::new(root, &state_path, threads, sender);
+        let build = button::Build::new(root, &state_path, threads, sender)
+            .with_checksum(self.checksum);
 
         if self.clean {
-            build.clean(self.dryrun)?;
+            build.clean(&rules_path, self.dryrun)?;
         }
 
-        let rules = Rules::from_path(&rules)?;
+        // Bring the build rules up to date first if they are generated.
+        let rules = build.regenerate(&rules_path, self.dryrun)?;
+
+        let build = build
+            .with_targets(self.targets)
+            .with_tags(self.tags.into_iter().collect())
+            .with_excluded_tags(self.exclude_tags.into_iter().collect());
 
         build.build(rules, self.dryrun)?;
 
         Ok(())
     }
+
+    /// Returns `true` if another event handler writes to stdout.
+    fn stdout_taken(&self) -> bool {
+        [&self.events_json, &self.trace, &self.junit, &self.html]
+            .iter()
+            .any(|path| path.as_deref() == Some(Path::new("-")))
+    }
+}
+
+/// Opens a writer for an event handler. A path of "-" means stdout.
+fn output_writer(path: &Path) -> Result<Box<dyn io::Write + Send>, Error> {
+    if path == Path::new("-") {
+        return Ok(Box::new(io::stdout()));
+    }
+
+    let f = fs::File::create(path)
+        .with_context(|_| format!("Failed to create '{}'", path.display()))?;
+
+    Ok(Box::new(io::BufWriter::new(f)))
 }
@@ -0,0 +1,82 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+use std::path::{Path, PathBuf};
+
+use structopt::StructOpt;
+
+use button::check::{check, Severity};
+use button::rules::Rules;
+use button::{Error, ResultExt};
+
+use crate::opts::GlobalOpts;
+use crate::paths;
+
+#[derive(StructOpt, Debug)]
+pub struct Check {
+    /// Path to the build rules. If not specified, finds "button.json" in the
+    /// current directory or parent directories.
+    #[structopt(long = "rules", short = "r", parse(from_os_str))]
+    rules: Option<PathBuf>,
+
+    /// Fail if there are any warnings.
+    #[structopt(long = "deny-warnings")]
+    deny_warnings: bool,
+}
+
+impl Check {
+    /// Checks the build rules for problems without building anything.
+    pub fn main(self, _global: &GlobalOpts) -> Result<(), Error> {
+        let path = paths::rules_or(self.rules)
+            .context("Failed to find build rules")?;
+
+        let root = path.parent().unwrap_or_else(|| Path::new("."));
+
+        let rules = Rules::from_path(&path).with_context(|_| {
+            format!("Failed loading rules from '{}'", path.display())
+        })?;
+
+        let problems = check(rules, root);
+
+        for problem in &problems {
+            println!("{}", problem);
+        }
+
+        let errors = problems
+            .iter()
+            .filter(|p| p.severity() == Severity::Error)
+            .count();
+        let warnings = problems.len() - errors;
+
+        if errors > 0 || (self.deny_warnings && warnings > 0) {
+            return Err(failure::format_err!(
+                "Found {} error(s) and {} warning(s) in '{}'",
+                errors,
+                warnings,
+                path.display()
+            ));
+        }
+
+        if warnings > 0 {
+            println!("Found {} warning(s)", warnings);
+        }
+
+        Ok(())
+    }
+}
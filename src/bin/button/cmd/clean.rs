@@ -67,7 +67,7 @@ impl Clean {
 
         let state_path = root.join(paths::STATE);
         let build = button::Build::new(root, &state_path, threads, sender);
-        build.clean(self.dryrun)?;
+        build.clean(&rules, self.dryrun)?;
 
         Ok(())
     }
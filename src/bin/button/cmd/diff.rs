@@ -0,0 +1,108 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json as json;
+use structopt::StructOpt;
+
+use button::build_graph::{BuildGraph, BuildGraphExt, FromRules};
+use button::diff::Changes;
+use button::rules::Rules;
+use button::{BuildState, Error, ResultExt};
+
+use crate::opts::GlobalOpts;
+use crate::paths;
+
+#[derive(StructOpt, Debug)]
+pub struct Diff {
+    /// Path to the old build rules. If not specified, uses the cached build
+    /// graph from the previous build.
+    #[structopt(parse(from_os_str))]
+    old: Option<PathBuf>,
+
+    /// Path to the new build rules. If not specified, finds "button.json" in
+    /// the current directory or parent directories.
+    #[structopt(parse(from_os_str))]
+    new: Option<PathBuf>,
+
+    /// Output the changes as JSON.
+    #[structopt(long = "json")]
+    json: bool,
+
+    /// Exit with a non-zero status if there are any changes.
+    #[structopt(long = "exit-code")]
+    exit_code: bool,
+}
+
+impl Diff {
+    /// Shows the changes between two sets of build rules.
+    pub fn main(self, _global: &GlobalOpts) -> Result<(), Error> {
+        let new_path = paths::rules_or(self.new.clone())
+            .context("Failed to find build rules")?;
+        let new = load_graph(&new_path)?;
+
+        let changes = if let Some(old_path) = &self.old {
+            let old = load_graph(old_path)?;
+            Changes::new(&old, &new)
+        } else {
+            let root = new_path.parent().unwrap_or_else(|| Path::new("."));
+            let state_path = root.join(paths::STATE);
+            let state =
+                BuildState::from_path(&state_path).with_context(|_| {
+                    format!(
+                        "Failed loading build state from '{}'",
+                        state_path.display()
+                    )
+                })?;
+
+            // The cached graph also has implicit dependencies. Only compare
+            // the part of it that came from the build rules.
+            Changes::new(&state.graph.explicit_subgraph(), &new)
+        };
+
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+
+        if self.json {
+            json::to_writer_pretty(&mut stdout, &changes)
+                .context("Failed serializing changes")?;
+            writeln!(stdout)?;
+        } else {
+            write!(stdout, "{}", changes)?;
+        }
+
+        stdout.flush()?;
+
+        if self.exit_code && !changes.is_empty() {
+            return Err(failure::format_err!("The build graphs differ"));
+        }
+
+        Ok(())
+    }
+}
+
+fn load_graph(path: &Path) -> Result<BuildGraph, Error> {
+    let rules = Rules::from_path(path).with_context(|_| {
+        format!("Failed loading rules from '{}'", path.display())
+    })?;
+
+    Ok(BuildGraph::from_rules(rules)?)
+}
@@ -27,7 +27,9 @@ use structopt::StructOpt;
 use button::{
     build_graph::{Edge, Node},
     graph::{Indexable, Neighbors, Nodes},
-    res, task, BuildState, Error, ResultExt,
+    res,
+    rules::Tags,
+    task, BuildState, Error, ResultExt,
 };
 
 use crate::opts::GlobalOpts;
@@ -49,6 +51,14 @@ struct CompleteRule {
 
     /// The sequence of tasks to execute.
     pub tasks: task::List,
+
+    /// Labels used to select which rules get built.
+    #[serde(default, skip_serializing_if = "Tags::is_empty")]
+    pub tags: Tags,
+
+    /// True if the task generates the build rules.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub generator: bool,
 }
 
 #[derive(StructOpt, Debug)]
@@ -109,12 +119,14 @@ impl Dump {
                         inputs_implicit,
                         outputs,
                         outputs_implicit,
-                        tasks: t.clone(),
+                        tasks: t.tasks.clone(),
+                        tags: t.tags.clone(),
+                        generator: t.generator,
                     };
 
                     rules.push(rule);
                 }
-                Node::Resource(_) => {}
+                Node::Resource(_) | Node::Alias(_) => {}
             }
         }
 
@@ -124,10 +136,3 @@ impl Dump {
         Ok(())
     }
 }
-
-// Possible queries:
-//  - What are the transitive outputs if this file is modified?
-//    * Involves BFS/DFS
-//  - What are the transitive dependencies of this file?
-//    * Involves BFS/DFS
-//  - What tasks is this file an input to?
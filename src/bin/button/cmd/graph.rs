@@ -20,11 +20,13 @@
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
+use regex::Regex;
 use structopt::StructOpt;
 
-use button::build_graph::{BuildGraph, FromRules};
-use button::graph::Graphviz;
+use button::build_graph::{BuildGraph, BuildGraphExt, FromRules};
+use button::graph::{Graphviz, Indexable, JsonGraph, Mermaid, Nodes};
 use button::rules::Rules;
 use button::{BuildState, Error, ResultExt};
 
@@ -46,12 +48,59 @@ pub struct Graph {
     /// displays the build graph derived from the build rules.
     #[structopt(long = "cached")]
     cached: bool,
+
+    /// The output format. "dot" is a GraphViz DOT file. "json" lists the
+    /// nodes and edges along with their types. "mermaid" is a Mermaid
+    /// flowchart that can be embedded in Markdown.
+    #[structopt(
+        long = "format",
+        default_value = "dot",
+        possible_values = &Format::variants(),
+    )]
+    format: Format,
+
+    /// Only show the nodes whose label matches this regular expression and
+    /// the nodes surrounding them. A resource's label is its path and a
+    /// task's label is its description. Can be specified more than once.
+    #[structopt(long = "select", short = "s", parse(try_from_str = Regex::new))]
+    select: Vec<Regex>,
+
+    /// When selecting nodes, the maximum number of edges to follow away from
+    /// the selected nodes.
+    #[structopt(long = "depth", default_value = "2")]
+    depth: usize,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum Format {
+    Dot,
+    Json,
+    Mermaid,
+}
+
+impl Format {
+    pub fn variants() -> [&'static str; 3] {
+        ["dot", "json", "mermaid"]
+    }
+}
+
+impl FromStr for Format {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(Format::Dot),
+            "json" => Ok(Format::Json),
+            "mermaid" => Ok(Format::Mermaid),
+            _ => Err("invalid graph format"),
+        }
+    }
 }
 
 impl Graph {
     /// Shows a pretty graph of the build.
     pub fn main(self, _global: &GlobalOpts) -> Result<(), Error> {
-        let rules = paths::rules_or(self.rules)
+        let rules = paths::rules_or(self.rules.clone())
             .context("Failed to find build rules")?;
 
         let build_graph = if self.cached {
@@ -74,20 +123,52 @@ impl Graph {
             BuildGraph::from_rules(rules)?
         };
 
+        let build_graph = if self.select.is_empty() {
+            build_graph
+        } else {
+            let selected: Vec<_> = build_graph
+                .nodes()
+                .filter(|&index| {
+                    let label = build_graph.node_from_index(index).label();
+                    self.select.iter().any(|re| re.is_match(&label))
+                })
+                .collect();
+
+            if selected.is_empty() {
+                return Err(failure::format_err!(
+                    "No nodes in the graph match the selection"
+                ));
+            }
+
+            build_graph.neighborhood(selected, self.depth)
+        };
+
         if let Some(output) = &self.output {
             let mut stream =
                 io::BufWriter::new(fs::File::create(&output).with_context(
                     |_| format!("Failed creating '{}'", output.display()),
                 )?);
-            build_graph.graphviz(&mut stream)?;
+            self.write(&build_graph, &mut stream)?;
             stream.flush() // Flush to catch write errors
         } else {
             let mut stdout = io::stdout();
-            build_graph.graphviz(&mut stdout.lock())?;
+            self.write(&build_graph, &mut stdout.lock())?;
             stdout.flush() // Flush to catch write errors
         }
-        .context("Failed writing GraphViz DOT file")?;
+        .context("Failed writing graph")?;
 
         Ok(())
     }
+
+    fn write(
+        &self,
+        graph: &BuildGraph,
+        f: &mut dyn io::Write,
+    ) -> Result<(), io::Error> {
+        match self.format {
+            Format::Dot => graph.graphviz(f),
+            Format::Json => graph.json(f),
+            Format::Mermaid => graph.mermaid(f),
+        }
+    }
 }
@@ -0,0 +1,97 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use serde_json as json;
+use structopt::StructOpt;
+
+use button::ninja::{self, Manifest};
+use button::{Error, ResultExt};
+
+use crate::opts::GlobalOpts;
+
+#[derive(StructOpt, Debug)]
+pub enum Import {
+    /// Converts a ninja build file to build rules.
+    #[structopt(name = "ninja")]
+    Ninja(Ninja),
+}
+
+impl Import {
+    pub fn main(self, global: &GlobalOpts) -> Result<(), Error> {
+        match self {
+            Import::Ninja(x) => x.main(global),
+        }
+    }
+}
+
+#[derive(StructOpt, Debug)]
+pub struct Ninja {
+    /// Path to the ninja file.
+    #[structopt(default_value = "build.ninja", parse(from_os_str))]
+    path: PathBuf,
+
+    /// Path to the output file. If not specified, writes to standard output.
+    /// Paths in the build rules are relative to the ninja file, so the output
+    /// should be placed next to it.
+    #[structopt(long = "output", short = "o", parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+impl Ninja {
+    pub fn main(self, _global: &GlobalOpts) -> Result<(), Error> {
+        let manifest = Manifest::from_path(&self.path).with_context(|_| {
+            format!("Failed loading '{}'", self.path.display())
+        })?;
+
+        let ninja::Converted { rules, untracked } = ninja::to_rules(&manifest)?;
+
+        if let Some(first) = untracked.first() {
+            eprintln!(
+                "Warning: {} build statement(s) (e.g., '{}') need a shell to \
+                 run. Their depfiles can't be used, so changes to the headers \
+                 they include won't cause a rebuild.",
+                untracked.len(),
+                first
+            );
+        }
+
+        if let Some(output) = &self.output {
+            let mut stream =
+                io::BufWriter::new(fs::File::create(output).with_context(
+                    |_| format!("Failed creating '{}'", output.display()),
+                )?);
+            json::to_writer_pretty(&mut stream, &rules)?;
+            writeln!(stream)?;
+            stream.flush() // Flush to catch write errors
+        } else {
+            let stdout = io::stdout();
+            let mut stdout = stdout.lock();
+            json::to_writer_pretty(&mut stdout, &rules)?;
+            writeln!(stdout)?;
+            stdout.flush() // Flush to catch write errors
+        }
+        .context("Failed writing build rules")?;
+
+        Ok(())
+    }
+}
@@ -19,19 +19,35 @@
 // THE SOFTWARE.
 
 mod build;
+mod check;
 mod clean;
+mod compdb;
+mod diff;
 mod dump;
+mod export;
 mod graph;
+mod import;
+mod query;
 mod replay;
 mod server;
+mod state;
+mod stats;
 mod test;
 
 pub use self::build::Build;
+pub use self::check::Check;
 pub use self::clean::Clean;
+pub use self::compdb::Compdb;
+pub use self::diff::Diff;
 pub use self::dump::Dump;
+pub use self::export::Export;
 pub use self::graph::Graph;
+pub use self::import::Import;
+pub use self::query::Query;
 pub use self::replay::Replay;
 pub use self::server::Server;
+pub use self::state::State;
+pub use self::stats::Stats;
 pub use self::test::Test;
 
 use structopt::StructOpt;
@@ -46,18 +62,42 @@ pub enum Command {
     #[structopt(name = "build")]
     Build(Build),
 
+    /// Checks the build rules for problems without building anything.
+    #[structopt(name = "check")]
+    Check(Check),
+
     /// Deletes all files created during the build.
     #[structopt(name = "clean")]
     Clean(Clean),
 
+    /// Generates a compilation database for C and C++ tooling.
+    #[structopt(name = "compdb")]
+    Compdb(Compdb),
+
+    /// Shows the changes between two versions of the build rules.
+    #[structopt(name = "diff")]
+    Diff(Diff),
+
     /// Dumps the build graph.
     #[structopt(name = "dump")]
     Dump(Dump),
 
+    /// Converts the build rules to build files for other build systems.
+    #[structopt(name = "export")]
+    Export(Export),
+
     /// Generates a graphviz file of the build graph.
     #[structopt(name = "graph")]
     Graph(Graph),
 
+    /// Converts build files from other build systems to build rules.
+    #[structopt(name = "import")]
+    Import(Import),
+
+    /// Answers questions about the dependencies of a file.
+    #[structopt(name = "query")]
+    Query(Query),
+
     /// Replays a build log file.
     #[structopt(name = "replay")]
     Replay(Replay),
@@ -66,6 +106,14 @@ pub enum Command {
     #[structopt(name = "server")]
     Server(Server),
 
+    /// Exports or imports the build state.
+    #[structopt(name = "state")]
+    State(State),
+
+    /// Shows statistics from previous builds.
+    #[structopt(name = "stats")]
+    Stats(Stats),
+
     /// Replays a build log file.
     #[structopt(name = "test")]
     Test(Test),
@@ -83,11 +131,19 @@ impl Command {
     pub fn main(self, global: &GlobalOpts) -> Result<(), Error> {
         match self {
             Command::Build(x) => x.main(global),
+            Command::Check(x) => x.main(global),
             Command::Clean(x) => x.main(global),
+            Command::Compdb(x) => x.main(global),
+            Command::Diff(x) => x.main(global),
             Command::Dump(x) => x.main(global),
+            Command::Export(x) => x.main(global),
             Command::Graph(x) => x.main(global),
+            Command::Import(x) => x.main(global),
+            Command::Query(x) => x.main(global),
             Command::Replay(x) => x.main(global),
             Command::Server(x) => x.main(global),
+            Command::State(x) => x.main(global),
+            Command::Stats(x) => x.main(global),
             Command::Test(x) => x.main(global),
         }
     }
@@ -0,0 +1,185 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+use std::io::{self, Write};
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde_json as json;
+use structopt::StructOpt;
+
+use button::build_graph::{BuildGraph, Node};
+use button::graph::{Algo, Indexable, Neighbors, NodeIndex};
+use button::{res, BuildState, Error, ResultExt};
+
+use crate::opts::GlobalOpts;
+use crate::paths;
+
+#[derive(StructOpt, Debug)]
+pub struct Query {
+    /// Path to the build rules. If not specified, finds "button.json" in the
+    /// current directory or parent directories.
+    #[structopt(long = "rules", short = "r", parse(from_os_str))]
+    rules: Option<PathBuf>,
+
+    /// Output the results as JSON instead of one per line.
+    #[structopt(long = "json")]
+    json: bool,
+
+    /// The query to run. "rdeps" finds the resources that are transitively
+    /// affected if the file changes. "deps" finds the resources the file
+    /// transitively depends on. "consumers" finds the tasks that take the file
+    /// as an input. "producer" finds the task that outputs the file.
+    #[structopt(possible_values = &Operation::variants())]
+    operation: Operation,
+
+    /// The file or directory to query. Relative paths are relative to the
+    /// current directory.
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+}
+
+impl Query {
+    /// Answers questions about the cached build graph.
+    pub fn main(self, _global: &GlobalOpts) -> Result<(), Error> {
+        let rules = paths::rules_or(self.rules)
+            .context("Failed to find build rules")?;
+
+        let root = rules.parent().unwrap_or_else(|| Path::new("."));
+
+        let state_path = root.join(paths::STATE);
+        let graph = BuildState::from_path(&state_path)
+            .with_context(|_| {
+                format!(
+                    "Failed loading build state from '{}'",
+                    state_path.display()
+                )
+            })?
+            .graph;
+
+        let path = relative_to(root, &self.path);
+        let index = find(&graph, &path).ok_or_else(|| {
+            failure::format_err!(
+                "'{}' is not in the build graph",
+                path.display()
+            )
+        })?;
+
+        let mut nodes: Vec<&Node> = match self.operation {
+            Operation::Rdeps => graph
+                .dfs(iter::once(index))
+                .filter(|&i| i != index)
+                .map(|i| graph.node_from_index(i))
+                .filter(|node| is_resource(node))
+                .collect(),
+            Operation::Deps => graph
+                .reverse_dfs(iter::once(index))
+                .filter(|&i| i != index)
+                .map(|i| graph.node_from_index(i))
+                .filter(|node| is_resource(node))
+                .collect(),
+            Operation::Consumers => graph
+                .outgoing(index)
+                .map(|(i, _)| graph.node_from_index(i))
+                .collect(),
+            Operation::Producer => graph
+                .incoming(index)
+                .map(|(i, _)| graph.node_from_index(i))
+                .collect(),
+        };
+
+        nodes.sort();
+        nodes.dedup();
+
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+
+        if self.json {
+            json::to_writer_pretty(&mut stdout, &nodes)
+                .context("Failed serializing query results")?;
+            writeln!(stdout)?;
+        } else {
+            for node in nodes {
+                writeln!(stdout, "{}", node.label())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A question to ask about a resource in the build graph.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Operation {
+    Rdeps,
+    Deps,
+    Consumers,
+    Producer,
+}
+
+impl Operation {
+    pub fn variants() -> [&'static str; 4] {
+        ["rdeps", "deps", "consumers", "producer"]
+    }
+}
+
+impl FromStr for Operation {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rdeps" => Ok(Operation::Rdeps),
+            "deps" => Ok(Operation::Deps),
+            "consumers" => Ok(Operation::Consumers),
+            "producer" => Ok(Operation::Producer),
+            _ => Err("invalid query"),
+        }
+    }
+}
+
+fn is_resource(node: &Node) -> bool {
+    match node {
+        Node::Resource(_) => true,
+        Node::Task(_) | Node::Alias(_) => false,
+    }
+}
+
+/// Resources in the build graph are relative to the project root. If the path
+/// exists, it is made relative to the root. Otherwise (e.g., for an output that
+/// has been cleaned), it is used as is.
+fn relative_to(root: &Path, path: &Path) -> PathBuf {
+    if let (Ok(root), Ok(path)) = (root.canonicalize(), path.canonicalize()) {
+        if let Ok(path) = path.strip_prefix(&root) {
+            return path.to_path_buf();
+        }
+    }
+
+    path.to_path_buf()
+}
+
+/// Finds the file or directory in the build graph.
+fn find(graph: &BuildGraph, path: &Path) -> Option<NodeIndex> {
+    let file = Node::Resource(res::File::new(path).into());
+    let dir = Node::Resource(res::Dir::new(path).into());
+
+    graph
+        .node_to_index(&file)
+        .or_else(|| graph.node_to_index(&dir))
+}
@@ -20,11 +20,16 @@
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
+use regex::Regex;
 use structopt::StructOpt;
 
 use button::{
-    events::{Console, EventHandler},
+    events::{
+        AnyHandler, Console, EventHandler, Filter, Html, JUnit, JsonLines,
+        Plain, TimeBound, Trace,
+    },
     Error, ResultExt,
 };
 
@@ -42,6 +47,49 @@ pub struct Replay {
     #[structopt(long = "realtime")]
     realtime: bool,
 
+    /// The format to replay the events in. "console" displays the build as
+    /// it happened. "plain" displays the build as plain lines of text. "json"
+    /// writes each event as a line of JSON. "trace" converts the events to the
+    /// Chrome Trace Event Format, which can be viewed in `chrome://tracing` or
+    /// Perfetto. "junit" converts the events to a JUnit XML report. "html"
+    /// creates a self-contained HTML report.
+    #[structopt(
+        long = "format",
+        default_value = "console",
+        possible_values = &Format::variants(),
+    )]
+    format: Format,
+
+    /// Path to the output file when not replaying to the console. If not
+    /// specified, writes to standard output.
+    #[structopt(long = "output", short = "o", parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    /// Only replay tasks that failed.
+    #[structopt(long = "failed")]
+    failed: bool,
+
+    /// Only replay tasks whose name matches this regular expression.
+    #[structopt(long = "match", parse(try_from_str = Regex::new))]
+    pattern: Option<Regex>,
+
+    /// Only replay tasks that started at or after this time. This is either
+    /// an offset from the start of the log (e.g., "1m 30s") or an RFC 3339
+    /// timestamp.
+    #[structopt(long = "since")]
+    since: Option<TimeBound>,
+
+    /// Only replay tasks that started at or before this time. This is either
+    /// an offset from the start of the log (e.g., "1m 30s") or an RFC 3339
+    /// timestamp.
+    #[structopt(long = "until")]
+    until: Option<TimeBound>,
+
+    /// Only replay tasks that were executed on this worker thread. Threads are
+    /// numbered starting at 0.
+    #[structopt(long = "thread")]
+    thread: Option<usize>,
+
     /// Print additional information.
     #[structopt(long = "verbose", short = "v")]
     verbose: bool,
@@ -56,10 +104,84 @@ impl Replay {
 
         let f = fs::File::open(path)
             .with_context(|_| format!("Failed opening '{}'", path.display()))?;
-        Console::new()
-            .read_bincode(io::BufReader::new(f), self.realtime)
+        let reader = io::BufReader::new(f);
+
+        let handler: AnyHandler = match self.format {
+            Format::Console => Console::new().into(),
+            Format::Plain => Plain::new(self.writer()?).into(),
+            Format::Json => JsonLines::new(self.writer()?).into(),
+            Format::Trace => Trace::new(self.writer()?).into(),
+            Format::JUnit => JUnit::new(self.writer()?).into(),
+            Format::Html => Html::new(self.writer()?).into(),
+        };
+
+        let mut filter = Filter::new(handler).with_failed(self.failed);
+
+        if let Some(pattern) = self.pattern {
+            filter = filter.with_pattern(pattern);
+        }
+
+        if let Some(since) = self.since {
+            filter = filter.with_since(since);
+        }
+
+        if let Some(until) = self.until {
+            filter = filter.with_until(until);
+        }
+
+        if let Some(thread) = self.thread {
+            filter = filter.with_thread(thread);
+        }
+
+        filter
+            .read_bincode(reader, self.realtime)
             .context("Failed reading events")?;
 
         Ok(())
     }
+
+    /// Opens the output file or stdout.
+    fn writer(&self) -> Result<Box<dyn io::Write + Send>, Error> {
+        Ok(match &self.output {
+            Some(output) => Box::new(io::BufWriter::new(
+                fs::File::create(output).with_context(|_| {
+                    format!("Failed creating '{}'", output.display())
+                })?,
+            )),
+            None => Box::new(io::stdout()),
+        })
+    }
+}
+
+/// The format to replay events in.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Format {
+    Console,
+    Plain,
+    Json,
+    Trace,
+    JUnit,
+    Html,
+}
+
+impl Format {
+    pub fn variants() -> [&'static str; 6] {
+        ["console", "plain", "json", "trace", "junit", "html"]
+    }
+}
+
+impl FromStr for Format {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "console" => Ok(Format::Console),
+            "plain" => Ok(Format::Plain),
+            "json" => Ok(Format::Json),
+            "trace" => Ok(Format::Trace),
+            "junit" => Ok(Format::JUnit),
+            "html" => Ok(Format::Html),
+            _ => Err("invalid replay format"),
+        }
+    }
 }
@@ -0,0 +1,193 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use structopt::StructOpt;
+
+use button::build_graph::{BuildGraph, FromRules};
+use button::{state, BuildState, Error, ResultExt, Rules};
+
+use crate::opts::GlobalOpts;
+use crate::paths;
+
+#[derive(StructOpt, Debug)]
+pub enum State {
+    /// Writes the build state as JSON.
+    #[structopt(name = "export")]
+    Export(Export),
+
+    /// Replaces the build state with one previously exported as JSON.
+    #[structopt(name = "import")]
+    Import(Import),
+
+    /// Recovers a corrupt build state from its backup or the build rules.
+    #[structopt(name = "repair")]
+    Repair(Repair),
+}
+
+impl State {
+    pub fn main(self, global: &GlobalOpts) -> Result<(), Error> {
+        match self {
+            State::Export(x) => x.main(global),
+            State::Import(x) => x.main(global),
+            State::Repair(x) => x.main(global),
+        }
+    }
+}
+
+#[derive(StructOpt, Debug)]
+pub struct Export {
+    /// Path to the build rules. If not specified, finds "button.json" in the
+    /// current directory or parent directories.
+    #[structopt(long = "rules", short = "r", parse(from_os_str))]
+    rules: Option<PathBuf>,
+
+    /// Path to the output file. If not specified, writes to standard output.
+    #[structopt(long = "output", short = "o", parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+impl Export {
+    pub fn main(self, _global: &GlobalOpts) -> Result<(), Error> {
+        let rules = paths::rules_or(self.rules)
+            .context("Failed to find build rules")?;
+
+        let root = rules.parent().unwrap_or_else(|| Path::new("."));
+
+        let state = BuildState::from_path(root.join(paths::STATE))?;
+
+        if let Some(output) = &self.output {
+            let mut stream =
+                io::BufWriter::new(fs::File::create(output).with_context(
+                    |_| format!("Failed creating '{}'", output.display()),
+                )?);
+            state.to_json(&mut stream)?;
+            stream.flush() // Flush to catch write errors
+        } else {
+            let mut stdout = io::stdout();
+            state.to_json(&mut stdout.lock())?;
+            stdout.flush() // Flush to catch write errors
+        }
+        .context("Failed writing build state")?;
+
+        Ok(())
+    }
+}
+
+#[derive(StructOpt, Debug)]
+pub struct Import {
+    /// Path to the build rules. If not specified, finds "button.json" in the
+    /// current directory or parent directories.
+    #[structopt(long = "rules", short = "r", parse(from_os_str))]
+    rules: Option<PathBuf>,
+
+    /// Path to the JSON file to import.
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+}
+
+impl Import {
+    pub fn main(self, _global: &GlobalOpts) -> Result<(), Error> {
+        let rules = paths::rules_or(self.rules)
+            .context("Failed to find build rules")?;
+
+        let root = rules.parent().unwrap_or_else(|| Path::new("."));
+
+        let path = self.path;
+
+        let f = fs::File::open(&path)
+            .with_context(|_| format!("Failed opening '{}'", path.display()))?;
+
+        let state = BuildState::from_json(io::BufReader::new(f)).with_context(
+            |_| format!("Failed importing '{}'", path.display()),
+        )?;
+
+        // Ensure the .button directory exists.
+        paths::init(root).context("Failed initializing .button directory")?;
+
+        state.write_to_path(root.join(paths::STATE))?;
+
+        Ok(())
+    }
+}
+
+#[derive(StructOpt, Debug)]
+pub struct Repair {
+    /// Path to the build rules. If not specified, finds "button.json" in the
+    /// current directory or parent directories.
+    #[structopt(long = "rules", short = "r", parse(from_os_str))]
+    rules: Option<PathBuf>,
+}
+
+impl Repair {
+    pub fn main(self, _global: &GlobalOpts) -> Result<(), Error> {
+        let rules = paths::rules_or(self.rules)
+            .context("Failed to find build rules")?;
+
+        let root = rules.parent().unwrap_or_else(|| Path::new("."));
+
+        let state_path = root.join(paths::STATE);
+
+        if !state_path.exists() {
+            println!("There is no build state to repair.");
+            return Ok(());
+        }
+
+        let err = match BuildState::from_path(&state_path) {
+            Ok(_) => {
+                println!("The build state is not corrupt.");
+                return Ok(());
+            }
+            Err(err) => err,
+        };
+
+        if !state::is_corrupt(&err) {
+            return Err(err.into());
+        }
+
+        let state = match BuildState::from_backup(&state_path) {
+            Ok(state) => {
+                println!("Restored the build state from the previous build.");
+                state
+            }
+            Err(_) => {
+                let rules = Rules::from_path(&rules)?;
+                let graph = BuildGraph::from_rules(rules)?;
+
+                println!(
+                    "Reconstructed the build state from the build rules. \
+                     Everything will be rebuilt."
+                );
+                BuildState::from_graph_owned(graph)
+            }
+        };
+
+        // Delete the corrupt state first so it doesn't replace the backup.
+        fs::remove_file(&state_path).with_context(|_| {
+            format!("Failed deleting '{}'", state_path.display())
+        })?;
+
+        state.write_to_path(&state_path)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use structopt::StructOpt;
+
+use button::stats::{BuildStats, Stats as BuildHistory};
+use button::{Error, ResultExt};
+
+use crate::opts::GlobalOpts;
+use crate::paths;
+
+#[derive(StructOpt, Debug)]
+pub struct Stats {
+    /// Path to the build rules. If not specified, finds "button.json" in the
+    /// current directory or parent directories.
+    #[structopt(long = "rules", short = "r", parse(from_os_str))]
+    rules: Option<PathBuf>,
+
+    /// The number of entries to show in each section.
+    #[structopt(long = "count", short = "n", default_value = "10")]
+    count: usize,
+}
+
+impl Stats {
+    pub fn main(self, _global: &GlobalOpts) -> Result<(), Error> {
+        let rules = paths::rules_or(self.rules)
+            .context("Failed to find build rules")?;
+
+        let root = rules.parent().unwrap_or_else(|| Path::new("."));
+
+        let stats = BuildHistory::from_path(root.join(paths::STATS))
+            .context("Failed loading build statistics")?;
+
+        if stats.builds().next().is_none() {
+            println!("No builds have been recorded yet.");
+            return Ok(());
+        }
+
+        let mut tasks: Vec<_> = stats
+            .tasks()
+            .filter_map(|(name, task)| task.average().map(|d| (name, task, d)))
+            .collect();
+
+        println!("Slowest tasks (average of recent runs):");
+        tasks.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(b.0)));
+        for (name, _, average) in tasks.iter().take(self.count) {
+            println!("  {:>10}  {}", secs(*average), name);
+        }
+
+        println!("\nMost frequently executed tasks:");
+        tasks.sort_by(|a, b| b.1.runs.cmp(&a.1.runs).then(a.0.cmp(b.0)));
+        for (name, task, _) in tasks.iter().take(self.count) {
+            if task.failures > 0 {
+                println!(
+                    "  {:>10}  {} ({} failed)",
+                    task.runs, name, task.failures
+                );
+            } else {
+                println!("  {:>10}  {}", task.runs, name);
+            }
+        }
+
+        let builds: Vec<_> = stats.builds().collect();
+
+        println!("\nRecent builds:");
+        let start = builds.len().saturating_sub(self.count);
+        for build in &builds[start..] {
+            println!(
+                "  {}  {:<6} {:>10}  {} executed, {} up-to-date, {} failed",
+                build.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                if build.success { "ok" } else { "FAILED" },
+                secs(build.duration),
+                build.executed,
+                build.cached,
+                build.failed,
+            );
+        }
+
+        // Compare the most recent builds with the ones before them to show
+        // whether builds are getting slower or faster.
+        let recent = &builds[start..];
+        let previous = &builds[start.saturating_sub(self.count)..start];
+
+        if let Some(recent_avg) = average(recent) {
+            print!(
+                "\nAverage build time: {} over the last {} build(s)",
+                secs(recent_avg),
+                recent.len()
+            );
+
+            match average(previous) {
+                Some(previous_avg) => println!(
+                    " ({} over the {} before that)",
+                    secs(previous_avg),
+                    previous.len()
+                ),
+                None => println!(),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Average duration of builds that actually executed something.
+fn average(builds: &[&BuildStats]) -> Option<Duration> {
+    let durations: Vec<_> = builds
+        .iter()
+        .filter(|b| b.executed > 0)
+        .map(|b| b.duration)
+        .collect();
+
+    if durations.is_empty() {
+        None
+    } else {
+        Some(durations.iter().sum::<Duration>() / durations.len() as u32)
+    }
+}
+
+fn secs(duration: Duration) -> String {
+    format!("{:.2}s", duration.as_secs_f64())
+}
@@ -45,6 +45,15 @@ pub const STATE: &str = ".button/state";
 /// Name of the file where the last build log is stored.
 pub const LOG: &str = ".button/log";
 
+/// Name of the directory where the output of each task is stored.
+pub const LOGS: &str = ".button/logs";
+
+/// Name of the file where statistics from previous builds are stored.
+pub const STATS: &str = ".button/stats";
+
+/// Name of the Unix domain socket that build events are published to.
+pub const EVENTS_SOCKET: &str = ".button/events.sock";
+
 /// Returns a path to the rules, starting at the given directory. The canonical
 /// name for the JSON rules file is "button.json". This function shall search
 /// for the file in the given starting directory and all parent directories.
@@ -25,7 +25,9 @@ use std::io;
 use std::path::Path;
 use std::sync::Mutex;
 
-use crate::build_graph::{BuildGraph, BuildGraphExt, Edge, FromRules, Node};
+use crate::build_graph::{
+    BuildGraph, BuildGraphExt, Edge, FromRules, Node, TaskNode,
+};
 use crate::detect::Detected;
 use crate::error::{
     BuildError, Error, ErrorKind, Fail, InvalidEdges, ResultExt,
@@ -34,10 +36,17 @@ use crate::events::{EventSender, EventSink};
 use crate::graph::{
     Algo, Edges, IndexSet, Indexable, Neighbors, NodeIndex, Nodes, Subgraph,
 };
-use crate::res::{self, Resource, ResourceState};
-use crate::rules::Rules;
-use crate::state::BuildState;
-use crate::task::{self, Task};
+use crate::res::{self, ChecksumAlgorithm, Resource, ResourceState};
+use crate::rules::{Rules, Tags};
+use crate::state::{
+    self,
+    journal::{self, Journal},
+    BuildState,
+};
+use crate::task::Task;
+
+/// Name of the build that brings generated build rules up to date.
+pub const REGENERATE: &str = "regenerate";
 
 /// A build failure. Contains each of the node indexes that failed and the
 /// associated error.
@@ -65,11 +74,16 @@ impl fmt::Display for BuildFailure {
 struct BuildContext<'a> {
     root: &'a Path,
     dryrun: bool,
+    checksum: ChecksumAlgorithm,
     graph: &'a BuildGraph,
     checksums: Mutex<HashMap<NodeIndex, ResourceState>>,
 
     // Detected inputs/outputs during the build.
     detected: Mutex<Vec<(NodeIndex, Detected)>>,
+
+    // Journal for recording changes to the state as they happen. This is
+    // `None` for dry runs.
+    journal: Option<Mutex<Journal>>,
 }
 
 fn delete_resources(
@@ -98,6 +112,7 @@ fn delete_resources(
                     if !graph.is_root_node(index)
                         && to_remove.contains(&index)
                         && checksums.contains_key(&index)
+                        && !graph.is_generated(index)
                     {
                         let result =
                             if dryrun { Ok(()) } else { r.delete(root) };
@@ -208,6 +223,7 @@ fn sync_added_inputs(
     inputs: HashSet<res::Any>,
     checksums: &mut HashMap<NodeIndex, ResourceState>,
     root: &Path,
+    checksum: ChecksumAlgorithm,
 ) {
     for input in inputs {
         let input = Node::Resource(input);
@@ -230,14 +246,14 @@ fn sync_added_inputs(
         } else {
             // Calculate the checksum so the build doesn't see this as
             // changed next time.
-            let checksum = input.as_res().state(root);
+            let state = input.as_res().state(root, checksum);
 
             // A new node! It's always valid to add a new node as an input.
             let index = graph.add_node(input);
             graph.add_edge(index, node, Edge::Implicit);
 
-            if let Ok(checksum) = checksum {
-                assert!(checksums.insert(index, checksum).is_none());
+            if let Ok(state) = state {
+                assert!(checksums.insert(index, state).is_none());
             }
         }
     }
@@ -253,13 +269,14 @@ fn sync_detected(
     detected: Vec<(NodeIndex, Detected)>,
     checksums: &mut HashMap<NodeIndex, ResourceState>,
     root: &Path,
+    checksum: ChecksumAlgorithm,
     _threads: usize,
     _dryrun: bool,
 ) -> Result<(), BuildError> {
     for (node, Detected { inputs, .. }) in detected {
         // Sync inputs
         sync_removed_inputs(graph, node, &inputs, checksums);
-        sync_added_inputs(graph, node, inputs, checksums, root);
+        sync_added_inputs(graph, node, inputs, checksums, root, checksum);
 
         // For detected outputs, we must only
         //  1. add an edge to new nodes.
@@ -271,6 +288,68 @@ fn sync_detected(
     Ok(())
 }
 
+/// Replays the journal left behind by an interrupted build onto the state.
+///
+/// The checksums recorded in the journal were already compared against during
+/// the interrupted build. Thus, resources that changed will not be seen as
+/// changed again. To make up for that, tasks consuming a changed resource are
+/// queued unless they finished afterwards.
+fn replay_journal(
+    state: &mut BuildState,
+    entries: Vec<journal::Entry>,
+    root: &Path,
+    checksum: ChecksumAlgorithm,
+) -> Result<(), BuildError> {
+    let mut pending = IndexSet::new();
+    let mut finished = IndexSet::new();
+    let mut detected = Vec::new();
+
+    for entry in entries {
+        match entry {
+            journal::Entry::Checksum(r, resource_state) => {
+                let index = match state.graph.node_to_index(&Node::Resource(r))
+                {
+                    Some(index) => index,
+                    None => continue,
+                };
+
+                if state.checksums.get(&index) != Some(&resource_state) {
+                    for (consumer, _) in state.graph.outgoing(index) {
+                        pending.insert(consumer);
+                        finished.remove(&consumer);
+                    }
+                }
+
+                state.checksums.insert(index, resource_state);
+            }
+            journal::Entry::Task(t, task_detected) => {
+                let index = match state.graph.node_to_index(&Node::Task(t)) {
+                    Some(index) => index,
+                    None => continue,
+                };
+
+                pending.remove(&index);
+                finished.insert(index);
+
+                detected.extend(task_detected.into_iter().map(|d| (index, d)));
+            }
+        }
+    }
+
+    state.queue.retain(|index| !finished.contains(index));
+    state.queue.extend(&pending);
+
+    sync_detected(
+        &mut state.graph,
+        detected,
+        &mut state.checksums,
+        root,
+        checksum,
+        0,
+        false,
+    )
+}
+
 /// Iterator over nodes that should be traversed during the build.
 ///
 /// Yields nodes that should be queued. Root resources are queued if they have
@@ -290,6 +369,7 @@ fn sync_detected(
 /// latency.
 struct DirtyNodes<'a> {
     root: &'a Path,
+    checksum: ChecksumAlgorithm,
     graph: &'a BuildGraph,
     nodes: <BuildGraph as Nodes<'a>>::Iter,
     checksums: &'a HashMap<NodeIndex, ResourceState>,
@@ -298,11 +378,13 @@ struct DirtyNodes<'a> {
 impl<'a> DirtyNodes<'a> {
     pub fn new(
         root: &'a Path,
+        checksum: ChecksumAlgorithm,
         graph: &'a BuildGraph,
         checksums: &'a HashMap<NodeIndex, ResourceState>,
     ) -> DirtyNodes<'a> {
         DirtyNodes {
             root,
+            checksum,
             graph,
             nodes: graph.nodes(),
             checksums,
@@ -319,7 +401,9 @@ impl<'a> Iterator for DirtyNodes<'a> {
                 match self.checksums.get(&index) {
                     Some(stored_state) => {
                         // Compute the current state and see if they differ.
-                        if let Ok(current_state) = r.state(self.root) {
+                        if let Ok(current_state) =
+                            r.state(self.root, self.checksum)
+                        {
                             if stored_state != &current_state {
                                 if let Some((parent, _)) =
                                     self.graph.incoming(index).next()
@@ -358,6 +442,19 @@ impl<'a> Iterator for DirtyNodes<'a> {
     }
 }
 
+/// The result of loading the build state from disk.
+enum LoadedState {
+    /// The build state was loaded successfully.
+    Found(BuildState),
+
+    /// There is no build state.
+    Missing,
+
+    /// The build state is corrupt.
+    Corrupt(Error),
+}
+
+#[derive(Clone)]
 pub struct Build<'a> {
     /// Path to the root of the project. This is used to ensure tasks start in
     /// the correct working directory.
@@ -370,8 +467,22 @@ pub struct Build<'a> {
     /// Number of threads to use for the build.
     threads: usize,
 
+    /// Algorithm used to compute resource checksums.
+    checksum: ChecksumAlgorithm,
+
     /// Channel for sending events to the event thread.
     event_sender: EventSender,
+
+    /// Aliases or resource paths to build. If empty, everything is built.
+    targets: Vec<String>,
+
+    /// Tasks with any of these tags are built. If empty, the default tags
+    /// from the rules are used instead.
+    tags: Tags,
+
+    /// Tasks with any of these tags are not built unless something else
+    /// depends on them.
+    excluded_tags: Tags,
 }
 
 impl<'a> Build<'a> {
@@ -386,42 +497,240 @@ impl<'a> Build<'a> {
             root,
             state,
             threads,
+            checksum: ChecksumAlgorithm::default(),
             event_sender,
+            targets: Vec::new(),
+            tags: Tags::new(),
+            excluded_tags: Tags::new(),
+        }
+    }
+
+    /// Sets the targets to build. A target is either the name of an alias or
+    /// the path to a resource relative to the root. Only the targets and the
+    /// nodes they depend on are built. Anything else that is out of date is
+    /// built by a later build.
+    pub fn with_targets(mut self, targets: Vec<String>) -> Build<'a> {
+        self.targets = targets;
+        self
+    }
+
+    /// Sets the tags of the tasks to build. Along with any targets, only
+    /// tasks with one of these tags and the nodes they depend on are built.
+    pub fn with_tags(mut self, tags: Tags) -> Build<'a> {
+        self.tags = tags;
+        self
+    }
+
+    /// Sets the tags of the tasks not to build. This only narrows down the
+    /// tasks selected by tags. Tasks that are needed by something else are
+    /// still built.
+    pub fn with_excluded_tags(mut self, tags: Tags) -> Build<'a> {
+        self.excluded_tags = tags;
+        self
+    }
+
+    /// Returns `true` if a task with the given tags is selected to be built.
+    /// Without any tags or targets, the default tags are used. Untagged tasks
+    /// are always built by default.
+    fn is_selected(&self, tags: &Tags, default_tags: &Tags) -> bool {
+        let selected = if !self.tags.is_empty() {
+            !self.tags.is_disjoint(tags)
+        } else if self.targets.is_empty() {
+            default_tags.is_empty()
+                || tags.is_empty()
+                || !default_tags.is_disjoint(tags)
+        } else {
+            false
+        };
+
+        selected && self.excluded_tags.is_disjoint(tags)
+    }
+
+    /// Finds the nodes needed to build the targets and the selected tasks.
+    /// Returns `None` if everything should be built.
+    fn needed_nodes(
+        &self,
+        graph: &BuildGraph,
+        default_tags: &Tags,
+    ) -> Result<Option<IndexSet<NodeIndex>>, BuildError> {
+        if self.targets.is_empty()
+            && self.tags.is_empty()
+            && self.excluded_tags.is_empty()
+            && default_tags.is_empty()
+        {
+            return Ok(None);
         }
+
+        let mut roots = Vec::new();
+
+        for target in &self.targets {
+            let candidates = [
+                Node::Alias(target.clone()),
+                Node::Resource(res::File::new(target).into()),
+                Node::Resource(res::Dir::new(target).into()),
+            ];
+
+            let index = candidates
+                .iter()
+                .find_map(|node| graph.node_to_index(node))
+                .ok_or_else(|| ErrorKind::UnknownTarget(target.clone()))?;
+
+            roots.push(index);
+        }
+
+        for index in graph.nodes() {
+            if let Node::Task(task) = graph.node_from_index(index) {
+                if self.is_selected(&task.tags, default_tags) {
+                    // The outputs are needed too so that they get checksummed
+                    // as part of this build.
+                    roots.push(index);
+                    roots.extend(
+                        graph.outgoing(index).map(|(output, _)| output),
+                    );
+                }
+            }
+        }
+
+        Ok(Some(graph.reverse_dfs(roots.into_iter()).collect()))
+    }
+
+    /// Sets the algorithm used to compute resource checksums. Changing the
+    /// algorithm between builds causes every resource to be seen as changed.
+    pub fn with_checksum(mut self, checksum: ChecksumAlgorithm) -> Build<'a> {
+        self.checksum = checksum;
+        self
     }
 
     /// Cleans all outputs of the build and the build state.
     ///
     /// This does *not* clean up build logs or anything else. Since the client
     /// is creating these things, it's up to the client to clean them up.
-    pub fn clean(&self, dryrun: bool) -> Result<(), BuildError> {
+    ///
+    /// The build rules at `rules_path` are only read if the first build was
+    /// interrupted before the build state could be written.
+    pub fn clean(
+        &self,
+        rules_path: &Path,
+        dryrun: bool,
+    ) -> Result<(), BuildError> {
         self.event_sender.begin_build(self.threads, "clean");
 
-        let result = self.clean_impl(dryrun);
+        let result = self.clean_impl(rules_path, dryrun);
 
         self.event_sender.end_build(&result);
         result
     }
 
-    pub fn clean_impl(&self, dryrun: bool) -> Result<(), BuildError> {
-        let state = match fs::File::open(self.state) {
-            Ok(f) => BuildState::from_reader(io::BufReader::new(f))
-                .with_context(|_| {
-                    ErrorKind::LoadState(self.state.to_path_buf())
-                })?,
+    /// Loads the build state from disk.
+    fn load_state(&self) -> Result<LoadedState, BuildError> {
+        match fs::File::open(self.state) {
+            Ok(f) => match BuildState::from_reader(io::BufReader::new(f)) {
+                Ok(state) => Ok(LoadedState::Found(state)),
+                Err(err) => {
+                    if state::is_corrupt(err.as_fail()) {
+                        Ok(LoadedState::Corrupt(err))
+                    } else {
+                        Err(err
+                            .context(ErrorKind::LoadState(
+                                self.state.to_path_buf(),
+                            ))
+                            .into())
+                    }
+                }
+            },
             Err(err) => {
                 if err.kind() == io::ErrorKind::NotFound {
-                    // Nothing to do if it doesn't exist.
-                    return Ok(());
+                    Ok(LoadedState::Missing)
                 } else {
                     // Some other fatal IO error occurred.
-                    return Err(err
+                    Err(err
                         .context(ErrorKind::LoadState(self.state.to_path_buf()))
-                        .into());
+                        .into())
+                }
+            }
+        }
+    }
+
+    /// Deletes the build state along with its backup and journal.
+    fn remove_state(&self) -> Result<(), BuildError> {
+        for path in &[
+            self.state.to_path_buf(),
+            state::backup_path(self.state),
+            journal::path(self.state),
+        ] {
+            match fs::remove_file(path) {
+                Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+                x => x,
+            }
+            .with_context(|_| ErrorKind::CleanState(path.clone()))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn clean_impl(
+        &self,
+        rules_path: &Path,
+        dryrun: bool,
+    ) -> Result<(), BuildError> {
+        // Outputs produced by an interrupted build need to be deleted too.
+        let entries = journal::read(self.state)
+            .with_context(|_| ErrorKind::LoadState(self.state.to_path_buf()))?;
+
+        let mut state = match self.load_state()? {
+            LoadedState::Found(state) => state,
+            LoadedState::Missing if entries.is_empty() => {
+                // Nothing to do if it doesn't exist.
+                if !dryrun {
+                    self.remove_state()?;
+                }
+
+                return Ok(());
+            }
+            LoadedState::Missing => {
+                // The first build was interrupted before it could write the
+                // state. Its outputs are only recorded in the journal, which
+                // refers to nodes of the graph built from the rules.
+                let rules = Rules::from_path(rules_path)?;
+                let graph = BuildGraph::from_rules(rules)
+                    .context(ErrorKind::BuildGraph)?;
+                BuildState::from_graph(graph)
+            }
+            LoadedState::Corrupt(err) => {
+                // Without the state, we don't know which resources we own.
+                // The backup from the previous build is the best we can do.
+                match BuildState::from_backup(self.state) {
+                    Ok(state) => {
+                        self.event_sender.recover_state(&err, true);
+
+                        if !dryrun {
+                            // Like when building, get rid of the corrupt state
+                            // so that it can never replace the backup.
+                            fs::remove_file(self.state).with_context(|_| {
+                                ErrorKind::LoadState(self.state.to_path_buf())
+                            })?;
+                        }
+
+                        state
+                    }
+                    Err(_) => {
+                        return Err(err
+                            .context(
+                                "No backup of the build state exists. Run \
+                                 `button state repair` to reconstruct it from \
+                                 the build rules.",
+                            )
+                            .context(ErrorKind::LoadState(
+                                self.state.to_path_buf(),
+                            ))
+                            .into());
+                    }
                 }
             }
         };
 
+        replay_journal(&mut state, entries, self.root, self.checksum)?;
+
         let root = self.root;
 
         // Delete resources in reverse topological order.
@@ -432,9 +741,11 @@ impl<'a> Build<'a> {
                     if let Node::Resource(r) = node {
                         // Only delete the resource if the state has been
                         // computed. A computed state indicates that the build
-                        // system "owns" the resource.
+                        // system "owns" the resource. The build rules, which
+                        // a generator task outputs, are kept.
                         if !state.graph.is_root_node(index)
                             && state.checksums.contains_key(&index)
+                            && !state.graph.is_generated(index)
                         {
                             let result =
                                 if dryrun { Ok(()) } else { r.delete(root) };
@@ -456,9 +767,7 @@ impl<'a> Build<'a> {
             .map_err(ErrorKind::DeleteErrors)?;
 
         // Delete the build state
-        fs::remove_file(self.state).with_context(|_| {
-            ErrorKind::CleanState(self.state.to_path_buf())
-        })?;
+        self.remove_state()?;
 
         Ok(())
     }
@@ -509,83 +818,228 @@ impl<'a> Build<'a> {
         result
     }
 
+    /// Reads the build rules, bringing them up to date first if they are
+    /// generated. The outputs of the generator rules, and anything they depend
+    /// on, are built by a separate build named `REGENERATE`. The rules are then
+    /// read again so that the main build sees the new ones.
+    pub fn regenerate(
+        &self,
+        rules_path: &Path,
+        dryrun: bool,
+    ) -> Result<Rules, BuildError> {
+        let rules = Rules::from_path(rules_path)?;
+
+        let generated: Vec<_> = rules
+            .iter()
+            .filter(|rule| rule.generator)
+            .flat_map(|rule| rule.outputs.iter().map(|r| r.to_string()))
+            .collect();
+
+        if generated.is_empty() {
+            return Ok(rules);
+        }
+
+        // Generator rules must be built regardless of which tags were asked
+        // for.
+        let build = Build {
+            targets: generated,
+            tags: Tags::new(),
+            excluded_tags: Tags::new(),
+            ..self.clone()
+        };
+
+        self.event_sender.begin_build(self.threads, REGENERATE);
+
+        let result = build.build_impl(rules, dryrun);
+
+        self.event_sender.end_build(&result);
+        result?;
+
+        Rules::from_path(rules_path)
+    }
+
+    /// Updates a loaded build state with the build graph.
+    fn sync_state(
+        &self,
+        state: &mut BuildState,
+        graph: &BuildGraph,
+        dryrun: bool,
+    ) -> Result<(), BuildError> {
+        sync_state(
+            state,
+            graph,
+            self.root,
+            self.threads,
+            self.event_sender.clone(),
+            dryrun,
+        )
+        .context(ErrorKind::SyncState)?;
+
+        Ok(())
+    }
+
     fn build_impl(&self, rules: Rules, dryrun: bool) -> Result<(), BuildError> {
+        let default_tags = rules.default_tags().clone();
+
         let graph =
             BuildGraph::from_rules(rules).context(ErrorKind::BuildGraph)?;
 
         // Load/create the build state.
-        let BuildState {
-            mut graph,
-            mut queue,
-            checksums,
-        } = {
-            match fs::File::open(self.state) {
-                Ok(f) => {
-                    let mut state =
-                        BuildState::from_reader(io::BufReader::new(f))
-                            .with_context(|_| {
-                                ErrorKind::LoadState(self.state.to_path_buf())
-                            })?;
+        let mut state = match self.load_state()? {
+            LoadedState::Found(mut state) => {
+                self.sync_state(&mut state, &graph, dryrun)?;
+                state
+            }
+            LoadedState::Missing => {
+                // If it doesn't exist, create it.
+                BuildState::from_graph(graph)
+            }
+            LoadedState::Corrupt(err) => {
+                let backup = BuildState::from_backup(self.state).ok();
 
-                    sync_state(
-                        &mut state,
-                        &graph,
-                        self.root,
-                        self.threads,
-                        self.event_sender.clone(),
-                        dryrun,
-                    )
-                    .context(ErrorKind::SyncState)?;
+                self.event_sender.recover_state(&err, backup.is_some());
 
-                    state
+                if !dryrun {
+                    // Get rid of the corrupt state so that it doesn't replace
+                    // the backup when the new state is written.
+                    fs::remove_file(self.state).with_context(|_| {
+                        ErrorKind::LoadState(self.state.to_path_buf())
+                    })?;
                 }
-                Err(err) => {
-                    if err.kind() == io::ErrorKind::NotFound {
-                        // If it doesn't exist, create it.
-                        BuildState::from_graph(graph)
-                    } else {
-                        // Some other fatal IO error occurred.
-                        return Err(err
-                            .context(ErrorKind::LoadState(
-                                self.state.to_path_buf(),
-                            ))
-                            .into());
+
+                match backup {
+                    Some(mut state) => {
+                        self.sync_state(&mut state, &graph, dryrun)?;
+                        state
+                    }
+                    None => {
+                        // We don't know which outputs we own anymore. Assume
+                        // we own all of them so that they get rebuilt.
+                        BuildState::from_graph_owned(graph)
                     }
                 }
             }
         };
 
-        queue.extend(DirtyNodes::new(self.root, &graph, &checksums));
+        // Pick up where an interrupted build left off.
+        let entries = journal::read(self.state)
+            .with_context(|_| ErrorKind::LoadState(self.state.to_path_buf()))?;
+        let replayed = !entries.is_empty();
+        replay_journal(&mut state, entries, self.root, self.checksum)?;
+
+        let BuildState {
+            mut graph,
+            mut queue,
+            checksums,
+        } = state;
+
+        queue.extend(DirtyNodes::new(
+            self.root,
+            self.checksum,
+            &graph,
+            &checksums,
+        ));
+
+        let needed = self.needed_nodes(&graph, &default_tags)?;
 
         if queue.is_empty() {
+            // Everything is up-to-date.
+            self.event_sender.plan(Vec::new(), task_count(&graph));
+
+            if replayed && !dryrun {
+                // Fold the journal into the state.
+                BuildState {
+                    graph,
+                    queue,
+                    checksums,
+                }
+                .write_to_path(self.state)?;
+            }
+
             // Don't bother traversing the graph if the queue is empty.
             return Ok(());
         }
 
+        let journal = if dryrun {
+            None
+        } else {
+            let journal = Journal::open(self.state).with_context(|_| {
+                ErrorKind::SaveState(self.state.to_path_buf())
+            })?;
+            Some(Mutex::new(journal))
+        };
+
         let context = BuildContext {
             root: self.root,
             dryrun,
+            checksum: self.checksum,
             graph: &graph,
             checksums: Mutex::new(checksums),
             detected: Mutex::new(Vec::new()),
+            journal,
+        };
+
+        // Out-of-date nodes that won't be visited because they aren't needed
+        // by the targets. These are queued for the next build.
+        let deferred: Vec<_> = match &needed {
+            Some(needed) => {
+                let dirty: IndexSet<_> =
+                    graph.dfs(queue.iter().cloned()).collect();
+
+                dirty
+                    .iter()
+                    .filter(|index| !needed.contains(index))
+                    .filter(|index| {
+                        queue.contains(index)
+                            || graph.incoming(*index).any(|(parent, _)| {
+                                needed.contains(&parent)
+                                    && dirty.contains(&parent)
+                            })
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
         };
 
         let result = {
+            let is_needed = |index: &NodeIndex| match &needed {
+                Some(needed) => needed.contains(index),
+                None => true,
+            };
+
             // Nodes that must get visited during the traversal.
-            let must_visit: IndexSet<_> = queue.iter().cloned().collect();
+            let must_visit: IndexSet<_> =
+                queue.iter().cloned().filter(is_needed).collect();
 
             // Create the subgraph from the queued nodes.
             let subgraph = Subgraph::new(
                 &graph,
-                graph.dfs(queue.into_iter()),
+                graph.dfs(queue.into_iter()).filter(is_needed),
                 graph.edges(),
             );
 
-            // Build the subgraph.
-            subgraph.traverse(
+            // Let the event handlers know what may get built.
+            let planned = subgraph
+                .nodes()
+                .filter_map(|index| match subgraph.node_from_index(index) {
+                    Node::Task(t) => Some(t.tasks.iter().cloned()),
+                    Node::Resource(_) | Node::Alias(_) => None,
+                })
+                .flatten()
+                .collect();
+            self.event_sender.plan(planned, task_count(&graph));
+
+            // Build the subgraph. Tasks that don't need to be executed are
+            // reported so that progress can still be tracked.
+            subgraph.traverse_with_skip(
                 |tid, index, node, events| {
                     build_node(&context, tid, index, node, events)
                 },
+                |_, node, events| {
+                    if let Node::Task(t) = node {
+                        events.skip(t.tasks.iter().cloned().collect());
+                    }
+                },
                 &must_visit,
                 self.threads,
                 false,
@@ -594,13 +1048,15 @@ impl<'a> Build<'a> {
         };
 
         let queue = {
+            let mut queue = deferred;
+
             if let Err(errors) = &result {
                 // Queue all failed tasks so that they get visited again next
                 // time.
-                errors.iter().map(|x| x.0).collect()
-            } else {
-                Vec::new()
+                queue.extend(errors.iter().map(|x| x.0));
             }
+
+            queue
         };
 
         let BuildContext {
@@ -620,6 +1076,7 @@ impl<'a> Build<'a> {
             detected,
             &mut checksums,
             self.root,
+            self.checksum,
             self.threads,
             dryrun,
         )?;
@@ -642,6 +1099,17 @@ impl<'a> Build<'a> {
     }
 }
 
+/// Returns the total number of tasks in the build graph.
+fn task_count(graph: &BuildGraph) -> usize {
+    graph
+        .nodes()
+        .filter_map(|index| match graph.node_from_index(index) {
+            Node::Task(t) => Some(t.tasks.len()),
+            Node::Resource(_) | Node::Alias(_) => None,
+        })
+        .sum()
+}
+
 fn build_node(
     context: &BuildContext<'_>,
     tid: usize,
@@ -652,6 +1120,9 @@ fn build_node(
     match node {
         Node::Resource(r) => build_resource(context, tid, index, r, events),
         Node::Task(t) => build_task(context, tid, index, t, events),
+
+        // There is nothing to build for an alias. It only groups other nodes.
+        Node::Alias(_) => Ok(true),
     }
 }
 
@@ -662,7 +1133,7 @@ fn build_resource(
     node: &res::Any,
     events: &EventSender,
 ) -> Result<bool, Error> {
-    let state = match node.state(context.root) {
+    let state = match node.state(context.root, context.checksum) {
         Ok(state) => state,
         Err(err) => {
             events.checksum_error(tid, node.clone(), &err);
@@ -670,6 +1141,14 @@ fn build_resource(
         }
     };
 
+    if let Some(journal) = &context.journal {
+        journal
+            .lock()
+            .unwrap()
+            .append(&journal::Entry::Checksum(node.clone(), state.clone()))
+            .context("Failed writing to the build state journal")?;
+    }
+
     let mut checksums = context.checksums.lock().unwrap();
 
     let ret = if let Some(prev_state) = checksums.get(&index) {
@@ -728,10 +1207,13 @@ fn build_task(
     context: &BuildContext<'_>,
     tid: usize,
     index: NodeIndex,
-    node: &task::List,
+    node: &TaskNode,
     events: &EventSender,
 ) -> Result<bool, Error> {
-    for task in node.iter() {
+    // Detected inputs/outputs of each task for the journal.
+    let mut journal_detected = Vec::new();
+
+    for task in node.tasks.iter() {
         let mut task_events = events.begin_task(tid, task.clone());
 
         if context.dryrun {
@@ -754,12 +1236,81 @@ fn build_task(
 
             task_events.finish(&result);
 
+            let detected = result?;
+
+            if context.journal.is_some() {
+                journal_detected.push(detected.clone());
+            }
+
             // Accumulate the detected inputs/outputs such that we can add them
             // to the implicit resources to the graph later. (We cannot modify
             // the build graph while traversing it.)
-            context.detected.lock().unwrap().push((index, result?));
+            context.detected.lock().unwrap().push((index, detected));
         }
     }
 
+    if let Some(journal) = &context.journal {
+        journal
+            .lock()
+            .unwrap()
+            .append(&journal::Entry::Task(node.clone(), journal_detected))
+            .context("Failed writing to the build state journal")?;
+    }
+
     Ok(true)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc;
+
+    use tempfile::TempDir;
+
+    use crate::events::Event;
+
+    #[test]
+    fn test_regenerate() -> Result<(), Error> {
+        let tempdir = TempDir::new()?;
+        let root = tempdir.path();
+
+        // The generator copies the real rules into place. The real rules
+        // have one more rule than the ones the build starts with.
+        let generator = r#"{
+            "inputs": [{"file": "rules.in"}],
+            "tasks": [{"copy": {"from": "rules.in", "to": "button.json"}}],
+            "outputs": [{"file": "button.json"}],
+            "generator": true
+        }"#;
+        let copy = r#"{
+            "inputs": [{"file": "a"}],
+            "tasks": [{"copy": {"from": "a", "to": "b"}}],
+            "outputs": [{"file": "b"}]
+        }"#;
+
+        let rules_path = root.join("button.json");
+        fs::write(&rules_path, format!("[{}]", generator))?;
+        fs::write(root.join("rules.in"), format!("[{}, {}]", generator, copy))?;
+
+        let state = root.join("state");
+        let (sender, receiver) = mpsc::channel();
+        let build = Build::new(root, &state, 1, sender);
+
+        let rules = build.regenerate(&rules_path, false)?;
+        assert_eq!(rules.iter().count(), 2);
+
+        drop(build);
+
+        let names: Vec<_> = receiver
+            .iter()
+            .filter_map(|(_, event)| match event {
+                Event::BeginBuild(e) => Some(e.name),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, vec![REGENERATE.to_owned()]);
+
+        Ok(())
+    }
+}
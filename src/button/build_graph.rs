@@ -18,18 +18,21 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
 use std::error;
 use std::fmt::{self, Debug, Display};
 use std::io;
 
 use serde::{Deserialize, Serialize};
+use serde_json as json;
 
 use crate::graph::{
-    Algo, Edges, Graph, GraphBase, Graphviz, Indexable, Neighbors, NodeIndex,
-    NodeTrait, Nodes, Subgraph,
+    Algo, Edges, Graph, GraphBase, Graphviz, Indexable, JsonGraph, Mermaid,
+    Neighbors, NodeIndex, NodeTrait, Nodes, Subgraph,
 };
 use crate::res;
-use crate::rules::{Rule, Rules};
+use crate::rules::{Rule, Rules, Tags, Target};
 use crate::task;
 
 /// A node in the graph.
@@ -38,7 +41,11 @@ use crate::task;
 )]
 pub enum Node {
     Resource(res::Any),
-    Task(task::List),
+    Task(TaskNode),
+
+    /// A named group of other nodes. This does not correspond to anything on
+    /// disk. Thus, it is never checksummed or deleted.
+    Alias(String),
 }
 
 impl Node {
@@ -53,10 +60,21 @@ impl Node {
     #[inline]
     pub fn as_task(&self) -> &task::List {
         match self {
-            Node::Task(t) => t,
+            Node::Task(t) => &t.tasks,
             _ => unreachable!(),
         }
     }
+
+    /// Returns a human-readable label for the node without the decoration
+    /// used by `Display`. For resources, this is the path. For tasks, this is
+    /// the task's display text.
+    pub fn label(&self) -> String {
+        match self {
+            Node::Resource(r) => r.to_string(),
+            Node::Task(t) => t.to_string(),
+            Node::Alias(a) => a.clone(),
+        }
+    }
 }
 
 impl Display for Node {
@@ -64,8 +82,58 @@ impl Display for Node {
         match self {
             Node::Resource(ref x) => write!(f, "({})", x),
             Node::Task(ref x) => write!(f, "[{}]", x),
+            Node::Alias(ref x) => write!(f, "<{}>", x),
+        }
+    }
+}
+
+/// A task node. This is the sequence of tasks from a rule along with the
+/// rule's metadata.
+#[derive(
+    Serialize, Deserialize, Clone, Ord, Eq, PartialOrd, PartialEq, Hash, Debug,
+)]
+pub struct TaskNode {
+    /// The sequence of tasks to execute.
+    pub tasks: task::List,
+
+    /// The rule's tags. Changing these changes the identity of the node.
+    pub tags: Tags,
+
+    /// True if the task generates the build rules. The outputs of such a
+    /// task are never deleted.
+    pub generator: bool,
+}
+
+impl TaskNode {
+    pub fn new(tasks: task::List) -> TaskNode {
+        TaskNode {
+            tasks,
+            tags: Tags::new(),
+            generator: false,
         }
     }
+
+    pub fn with_tags(mut self, tags: Tags) -> TaskNode {
+        self.tags = tags;
+        self
+    }
+
+    pub fn with_generator(mut self, generator: bool) -> TaskNode {
+        self.generator = generator;
+        self
+    }
+}
+
+impl From<task::List> for TaskNode {
+    fn from(tasks: task::List) -> TaskNode {
+        TaskNode::new(tasks)
+    }
+}
+
+impl Display for TaskNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.tasks, f)
+    }
 }
 
 #[derive(
@@ -283,30 +351,55 @@ pub trait FromRules: Sized {
 
 impl FromRules for BuildGraph {
     fn from_rules(rules: Rules) -> Result<BuildGraph, Error> {
-        let mut g = Graph::new();
-
-        for rule in rules {
-            let Rule {
-                inputs,
-                outputs,
-                tasks,
-            } = rule;
-
-            let task = g.add_node(Node::Task(tasks));
+        Ok(check_races(check_cycles(from_rules_unchecked(rules))?)?)
+    }
+}
 
-            for r in inputs {
-                let node = g.add_node(Node::Resource(r));
-                g.add_edge(node, task, Edge::Explicit);
-            }
+/// Creates a build graph from the given rules without checking it for race
+/// conditions or cycles.
+pub(crate) fn from_rules_unchecked(rules: Rules) -> BuildGraph {
+    let mut g = Graph::new();
+
+    for (name, targets) in rules.aliases() {
+        let alias = g.add_node(Node::Alias(name.clone()));
+
+        for target in targets {
+            let node = g.add_node(match target {
+                Target::File(x) => Node::Resource(x.clone().into()),
+                Target::Dir(x) => Node::Resource(x.clone().into()),
+                Target::Alias(x) => Node::Alias(x.clone()),
+            });
+            g.add_edge(node, alias, Edge::Explicit);
+        }
+    }
 
-            for r in outputs {
-                let node = g.add_node(Node::Resource(r));
-                g.add_edge(task, node, Edge::Explicit);
-            }
+    for rule in rules {
+        let Rule {
+            inputs,
+            outputs,
+            tasks,
+            tags,
+            generator,
+        } = rule;
+
+        let task = g.add_node(Node::Task(
+            TaskNode::new(tasks)
+                .with_tags(tags)
+                .with_generator(generator),
+        ));
+
+        for r in inputs {
+            let node = g.add_node(Node::Resource(r));
+            g.add_edge(node, task, Edge::Explicit);
         }
 
-        Ok(check_races(check_cycles(g)?)?)
+        for r in outputs {
+            let node = g.add_node(Node::Resource(r));
+            g.add_edge(task, node, Edge::Explicit);
+        }
     }
+
+    g
 }
 
 /// Functions that specifically operate on a graph whose nodes are of type
@@ -346,6 +439,67 @@ pub trait BuildGraphExt<'a>:
 
         Subgraph::new(self, nodes, edges)
     }
+
+    /// Returns true if the node is an output of a generator task. These are
+    /// never deleted since the build rules are among them.
+    fn is_generated(&'a self, index: NodeIndex) -> bool {
+        self.incoming(index)
+            .any(|(task, _)| match self.node_from_index(task) {
+                Node::Task(t) => t.generator,
+                Node::Resource(_) | Node::Alias(_) => false,
+            })
+    }
+
+    /// Returns a new graph containing the given nodes and every node within
+    /// `depth` edges of them, following edges in either direction. Only the
+    /// edges between the included nodes are kept.
+    fn neighborhood<I>(&'a self, roots: I, depth: usize) -> BuildGraph
+    where
+        I: IntoIterator<Item = NodeIndex>,
+    {
+        let mut g = BuildGraph::new();
+
+        // Maps indices in this graph to indices in the new graph.
+        let mut visited = HashMap::new();
+        let mut queue = VecDeque::new();
+
+        for root in roots {
+            if let Entry::Vacant(entry) = visited.entry(root) {
+                entry.insert(g.add_node(self.node_from_index(root).clone()));
+                queue.push_back((root, 0));
+            }
+        }
+
+        while let Some((node, distance)) = queue.pop_front() {
+            if distance >= depth {
+                continue;
+            }
+
+            let neighbors = self
+                .incoming(node)
+                .chain(self.outgoing(node))
+                .map(|(neighbor, _)| neighbor);
+
+            for neighbor in neighbors {
+                if let Entry::Vacant(entry) = visited.entry(neighbor) {
+                    entry.insert(
+                        g.add_node(self.node_from_index(neighbor).clone()),
+                    );
+                    queue.push_back((neighbor, distance + 1));
+                }
+            }
+        }
+
+        for index in self.edges() {
+            let ((from, to), weight) = self.edge_from_index(index);
+            if let (Some(&a), Some(&b)) = (visited.get(&from), visited.get(&to))
+            {
+                g.add_edge(a, b, *weight);
+            }
+        }
+
+        g
+    }
 }
 
 impl<'a> BuildGraphExt<'a> for BuildGraph {}
@@ -383,7 +537,7 @@ impl Graphviz for BuildGraph {
                 Node::Resource(ref resource) => {
                     writeln!(f, "        N{} [label={}];", i, resource)?;
                 }
-                Node::Task(_) => {}
+                Node::Task(_) | Node::Alias(_) => {}
             };
         }
         writeln!(f, "    }}")?;
@@ -397,7 +551,7 @@ impl Graphviz for BuildGraph {
         for index in self.nodes() {
             let node = self.node_from_index(index);
             match node {
-                Node::Resource(_) => {}
+                Node::Resource(_) | Node::Alias(_) => {}
                 Node::Task(ref task) => {
                     writeln!(
                         f,
@@ -410,6 +564,24 @@ impl Graphviz for BuildGraph {
         }
         writeln!(f, "    }}")?;
 
+        // Style and label every alias
+        writeln!(f, "    subgraph {{")?;
+        writeln!(
+            f,
+            "        node [shape=hexagon, fillcolor=palegreen, style=filled];"
+        )?;
+        for index in self.nodes() {
+            if let Node::Alias(ref alias) = self.node_from_index(index) {
+                writeln!(
+                    f,
+                    "        N{} [label=\"{}\"];",
+                    index,
+                    escape_label(alias)
+                )?;
+            }
+        }
+        writeln!(f, "    }}")?;
+
         // Edges
         for index in self.edges() {
             let (edge, weight) = self.edge_from_index(index);
@@ -425,11 +597,127 @@ impl Graphviz for BuildGraph {
     }
 }
 
+impl Mermaid for BuildGraph {
+    fn mermaid(&self, f: &mut dyn io::Write) -> Result<(), io::Error> {
+        fn escape_label(s: &str) -> String {
+            s.replace('"', "#quot;")
+        }
+
+        writeln!(f, "graph LR")?;
+
+        for index in self.nodes() {
+            let node = self.node_from_index(index);
+            let label = escape_label(&node.label());
+            match node {
+                Node::Resource(_) => {
+                    writeln!(f, "    N{}([\"{}\"])", index, label)?;
+                }
+                Node::Task(_) => {
+                    writeln!(f, "    N{}[\"{}\"]", index, label)?;
+                }
+                Node::Alias(_) => {
+                    writeln!(f, "    N{}{{{{\"{}\"}}}}", index, label)?;
+                }
+            };
+        }
+
+        for index in self.edges() {
+            let (edge, weight) = self.edge_from_index(index);
+            let arrow = match weight {
+                Edge::Explicit => "-->",
+                Edge::Implicit => "-.->",
+            };
+
+            writeln!(f, "    N{} {} N{}", edge.0, arrow, edge.1)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A node as it appears in the JSON representation of the graph.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum JsonNode<'a> {
+    Resource {
+        id: usize,
+        label: String,
+        resource: &'a res::Any,
+    },
+    Task {
+        id: usize,
+        label: String,
+        tasks: &'a task::List,
+        tags: &'a Tags,
+    },
+    Alias {
+        id: usize,
+        label: String,
+    },
+}
+
+/// An edge as it appears in the JSON representation of the graph.
+#[derive(Serialize)]
+struct JsonEdge {
+    from: usize,
+    to: usize,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Serialize)]
+struct JsonGraphRepr<'a> {
+    nodes: Vec<JsonNode<'a>>,
+    edges: Vec<JsonEdge>,
+}
+
+impl JsonGraph for BuildGraph {
+    fn json(&self, f: &mut dyn io::Write) -> Result<(), io::Error> {
+        let nodes = self
+            .nodes()
+            .map(|index| {
+                let node = self.node_from_index(index);
+                let id = index.into();
+                let label = node.label();
+                match node {
+                    Node::Resource(resource) => JsonNode::Resource {
+                        id,
+                        label,
+                        resource,
+                    },
+                    Node::Task(task) => JsonNode::Task {
+                        id,
+                        label,
+                        tasks: &task.tasks,
+                        tags: &task.tags,
+                    },
+                    Node::Alias(_) => JsonNode::Alias { id, label },
+                }
+            })
+            .collect();
+
+        let edges = self
+            .edges()
+            .map(|index| {
+                let ((from, to), weight) = self.edge_from_index(index);
+                JsonEdge {
+                    from: from.into(),
+                    to: to.into(),
+                    kind: weight.to_string(),
+                }
+            })
+            .collect();
+
+        json::to_writer_pretty(&mut *f, &JsonGraphRepr { nodes, edges })?;
+        writeln!(f)
+    }
+}
+
 /// Checks for race conditions in the graph. That is, if any node has two or
 /// more parents. In such a case where two tasks output the same resource,
 /// depending on the order in which they get executed, they could be overwriting
 /// each other's output.
-fn check_races(graph: BuildGraph) -> Result<BuildGraph, RaceError> {
+pub(crate) fn check_races(graph: BuildGraph) -> Result<BuildGraph, RaceError> {
     let mut races = Vec::new();
 
     for i in graph.nodes() {
@@ -442,7 +730,7 @@ fn check_races(graph: BuildGraph) -> Result<BuildGraph, RaceError> {
                     races.push(Race::new(r.clone(), incoming));
                 }
             }
-            Node::Task(_) => {}
+            Node::Task(_) | Node::Alias(_) => {}
         };
     }
 
@@ -455,7 +743,7 @@ fn check_races(graph: BuildGraph) -> Result<BuildGraph, RaceError> {
 
 /// Checks for cycles in the graph using Tarjan's algorithm for finding strongly
 /// connected components.
-fn check_cycles<N, E>(
+pub(crate) fn check_cycles<N, E>(
     graph: Graph<N, E>,
 ) -> Result<Graph<N, E>, CyclesError<N, E>>
 where
@@ -636,4 +924,154 @@ mod tests {
             1
         );
     }
+
+    fn chain() -> BuildGraph {
+        let data = r#"[
+        {
+            "inputs": [{"file": "foo.c"}],
+            "tasks": [{"command": {"program": "cc", "args": ["-c", "foo.c"]}}],
+            "outputs": [{"file": "foo.o"}]
+        },
+        {
+            "inputs": [{"file": "foo.o"}],
+            "tasks": [{"command": {"program": "cc", "args": ["foo.o"]}}],
+            "outputs": [{"file": "foo"}]
+        }
+        ]"#;
+
+        BuildGraph::from_rules(Rules::from_str(data).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_neighborhood() {
+        let graph = chain();
+
+        let root = graph
+            .node_to_index(&Node::Resource(File::from("foo.o").into()))
+            .unwrap();
+
+        let sub = graph.neighborhood(Some(root), 0);
+        assert_eq!(sub.node_count(), 1);
+        assert_eq!(sub.edge_count(), 0);
+
+        // Both tasks are adjacent to the object file.
+        let sub = graph.neighborhood(Some(root), 1);
+        assert_eq!(sub.node_count(), 3);
+        assert_eq!(sub.edge_count(), 2);
+
+        // The compiler, the source, and the final output are two edges away.
+        let sub = graph.neighborhood(Some(root), 2);
+        assert_eq!(sub.node_count(), graph.node_count());
+        assert_eq!(sub.edge_count(), graph.edge_count());
+    }
+
+    #[test]
+    fn test_formats() {
+        let graph = chain();
+
+        let mut mermaid = Vec::new();
+        graph.mermaid(&mut mermaid).unwrap();
+        let mermaid = String::from_utf8(mermaid).unwrap();
+        assert!(mermaid.starts_with("graph LR\n"));
+        assert!(mermaid.contains("([\"foo.o\"])"));
+        assert_eq!(mermaid.matches(" --> ").count(), 6);
+
+        let mut output = Vec::new();
+        graph.json(&mut output).unwrap();
+        let value: json::Value = json::from_slice(&output).unwrap();
+        assert_eq!(value["nodes"].as_array().unwrap().len(), 6);
+        assert_eq!(value["edges"].as_array().unwrap().len(), 6);
+        assert_eq!(value["edges"][0]["type"], "explicit");
+    }
+
+    #[test]
+    fn test_aliases() {
+        let data = r#"{
+            "rules": [{
+                "inputs": [{"file": "foo.c"}],
+                "tasks": [{"command": {"program": "cc", "args": ["foo.c"]}}],
+                "outputs": [{"file": "foo"}]
+            }],
+            "aliases": {
+                "all": [{"alias": "bin"}],
+                "bin": [{"file": "foo"}]
+            }
+        }"#;
+
+        let graph =
+            BuildGraph::from_rules(Rules::from_str(data).unwrap()).unwrap();
+
+        let foo = graph
+            .node_to_index(&Node::Resource(File::from("foo").into()))
+            .unwrap();
+        let bin = graph.node_to_index(&Node::Alias("bin".into())).unwrap();
+        let all = graph.node_to_index(&Node::Alias("all".into())).unwrap();
+
+        assert!(graph.contains_edge_by_index(foo, bin));
+        assert!(graph.contains_edge_by_index(bin, all));
+    }
+
+    #[test]
+    fn test_tags() {
+        let data = r#"[
+            {
+                "tasks": [{"command": {"program": "test.sh", "args": []}}],
+                "tags": ["test"]
+            },
+            {
+                "tasks": [{"command": {"program": "test.sh", "args": []}}],
+                "tags": ["slow", "test"]
+            }
+        ]"#;
+
+        let graph =
+            BuildGraph::from_rules(Rules::from_str(data).unwrap()).unwrap();
+
+        let mut tags: Vec<_> = graph
+            .nodes()
+            .filter_map(|index| match graph.node_from_index(index) {
+                Node::Task(t) => Some(t.tags.iter().cloned().collect()),
+                _ => None,
+            })
+            .collect::<Vec<Vec<String>>>();
+        tags.sort();
+
+        // The same tasks with different tags are different nodes.
+        assert_eq!(
+            tags,
+            vec![
+                vec!["slow".to_string(), "test".to_string()],
+                vec!["test".to_string()]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generator() {
+        let data = r#"[
+            {
+                "inputs": [{"file": "gen.sh"}],
+                "tasks": [{"command": {"program": "gen.sh", "args": []}}],
+                "outputs": [{"file": "button.json"}],
+                "generator": true
+            },
+            {
+                "tasks": [{"command": {"program": "cc", "args": []}}],
+                "outputs": [{"file": "foo"}]
+            }
+        ]"#;
+
+        let graph =
+            BuildGraph::from_rules(Rules::from_str(data).unwrap()).unwrap();
+
+        let index = |path| {
+            graph
+                .node_to_index(&Node::Resource(File::from(path).into()))
+                .unwrap()
+        };
+
+        assert!(graph.is_generated(index("button.json")));
+        assert!(!graph.is_generated(index("foo")));
+        assert!(!graph.is_generated(index("gen.sh")));
+    }
 }
@@ -0,0 +1,500 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+//! Validation of build rules without running a build.
+//!
+//! Cycles and race conditions are normally only found when the build graph is
+//! created at the start of a build. This finds those along with other mistakes
+//! in the build rules that would otherwise only show up as a failed or
+//! incorrect build.
+
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path};
+
+use derive_more::Display;
+
+use crate::build_graph::{self, BuildGraph, Node};
+use crate::graph::{Indexable, Neighbors, Nodes};
+use crate::res;
+use crate::rules::{Rules, Target};
+use crate::task::{self, Task};
+use crate::util::PathExt;
+
+/// How bad a problem is.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Display)]
+pub enum Severity {
+    /// The build will fail or will be incorrect.
+    #[display(fmt = "error")]
+    Error,
+
+    /// The build might work, but probably not everywhere.
+    #[display(fmt = "warning")]
+    Warning,
+}
+
+/// The kind of problem found in the build rules.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Display)]
+pub enum Lint {
+    /// A cycle in the build graph.
+    #[display(fmt = "cycle")]
+    Cycle,
+
+    /// A resource that is the output of more than one task.
+    #[display(fmt = "race")]
+    Race,
+
+    /// A path that is both an output and, as a different type of resource, a
+    /// root input.
+    #[display(fmt = "output-as-source")]
+    OutputAsSource,
+
+    /// An absolute path to a resource.
+    #[display(fmt = "absolute-path")]
+    AbsolutePath,
+
+    /// A path to a resource outside of the project root.
+    #[display(fmt = "outside-root")]
+    OutsideRoot,
+
+    /// A program that cannot be found or read.
+    #[display(fmt = "missing-program")]
+    MissingProgram,
+
+    /// A copy whose destination directory is not created by any task.
+    #[display(fmt = "missing-directory")]
+    MissingDirectory,
+
+    /// A rule that is identical to an earlier rule.
+    #[display(fmt = "duplicate-rule")]
+    DuplicateRule,
+
+    /// An alias that refers to an alias that isn't defined.
+    #[display(fmt = "unknown-alias")]
+    UnknownAlias,
+}
+
+impl Lint {
+    pub fn severity(self) -> Severity {
+        match self {
+            Lint::Cycle
+            | Lint::Race
+            | Lint::OutputAsSource
+            | Lint::MissingProgram
+            | Lint::UnknownAlias => Severity::Error,
+            Lint::AbsolutePath
+            | Lint::OutsideRoot
+            | Lint::MissingDirectory
+            | Lint::DuplicateRule => Severity::Warning,
+        }
+    }
+}
+
+/// A problem found in the build rules.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Problem {
+    pub lint: Lint,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(lint: Lint, message: String) -> Problem {
+        Problem { lint, message }
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.lint.severity()
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity(), self.lint, self.message)
+    }
+}
+
+/// Checks the build rules for problems. Relative paths are resolved against
+/// `root`.
+pub fn check(rules: Rules, root: &Path) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    duplicate_rules(&rules, &mut problems);
+    bad_paths(&rules, &mut problems);
+    missing_programs(&rules, root, &mut problems);
+    unknown_aliases(&rules, &mut problems);
+
+    let copy_dirs = copy_dirs(&rules);
+
+    let graph = match build_graph::check_cycles(
+        build_graph::from_rules_unchecked(rules),
+    ) {
+        Ok(graph) => graph,
+        Err(err) => {
+            for cycle in &err.cycles {
+                // Cycles are listed in reverse topological order.
+                let nodes: Vec<_> = cycle
+                    .iter()
+                    .rev()
+                    .map(|index| err.graph.node_from_index(*index).to_string())
+                    .collect();
+
+                problems.push(Problem::new(
+                    Lint::Cycle,
+                    format!("{} -> {}", nodes.join(" -> "), nodes[0]),
+                ));
+            }
+
+            err.graph
+        }
+    };
+
+    outputs_as_sources(&graph, &mut problems);
+    missing_dirs(&graph, &copy_dirs, root, &mut problems);
+
+    if let Err(err) = build_graph::check_races(graph) {
+        for race in err.races {
+            problems.push(Problem::new(
+                Lint::Race,
+                format!(
+                    "'{}' is the output of {} tasks",
+                    race.node, race.count
+                ),
+            ));
+        }
+    }
+
+    problems
+}
+
+fn duplicate_rules(rules: &Rules, problems: &mut Vec<Problem>) {
+    let mut seen = HashMap::new();
+
+    for (i, rule) in rules.iter().enumerate() {
+        let key = (&rule.inputs, &rule.outputs, &rule.tasks);
+
+        if let Some(first) = seen.get(&key) {
+            problems.push(Problem::new(
+                Lint::DuplicateRule,
+                format!("rule {} is identical to rule {}", i + 1, first + 1),
+            ));
+        } else {
+            seen.insert(key, i);
+        }
+    }
+}
+
+fn unknown_aliases(rules: &Rules, problems: &mut Vec<Problem>) {
+    let aliases = rules.aliases();
+
+    for (name, targets) in aliases {
+        for target in targets {
+            if let Target::Alias(alias) = target {
+                if !aliases.contains_key(alias) {
+                    problems.push(Problem::new(
+                        Lint::UnknownAlias,
+                        format!(
+                            "alias '{}' refers to '{}', which is not defined",
+                            name, alias
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Returns the programs used by all commands.
+fn programs(rules: &Rules) -> BTreeSet<&Path> {
+    rules
+        .iter()
+        .flat_map(|rule| rule.tasks.iter())
+        .filter_map(|task| match task {
+            task::Any::Command(command) => {
+                Some(command.process().program.as_path())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn bad_paths(rules: &Rules, problems: &mut Vec<Problem>) {
+    // Programs are commonly absolute paths outside of the project. Those are
+    // checked separately.
+    let programs: BTreeSet<_> =
+        programs(rules).into_iter().map(|p| p.normalize()).collect();
+
+    let resources: BTreeSet<_> = rules
+        .iter()
+        .flat_map(|rule| rule.inputs.iter().chain(rule.outputs.iter()))
+        .filter(|r| !programs.contains(r.path()))
+        .collect();
+
+    for r in resources {
+        let path = r.path();
+
+        if path.is_absolute() {
+            problems.push(Problem::new(
+                Lint::AbsolutePath,
+                format!("'{}' is an absolute path", r),
+            ));
+        } else if path.components().next() == Some(Component::ParentDir) {
+            problems.push(Problem::new(
+                Lint::OutsideRoot,
+                format!("'{}' is outside of the project root", r),
+            ));
+        }
+    }
+}
+
+fn missing_programs(rules: &Rules, root: &Path, problems: &mut Vec<Problem>) {
+    for program in programs(rules) {
+        if !find_program(program, root) {
+            problems.push(Problem::new(
+                Lint::MissingProgram,
+                format!(
+                    "program '{}' does not exist or is not readable",
+                    program.display()
+                ),
+            ));
+        }
+    }
+}
+
+/// Returns true if the program can be found. Bare program names are searched
+/// for in the `PATH`. Otherwise, the path is relative to the project root.
+fn find_program(program: &Path, root: &Path) -> bool {
+    fn readable(path: &Path) -> bool {
+        path.is_file() && fs::File::open(path).is_ok()
+    }
+
+    if program.components().count() > 1 {
+        return readable(&root.join(program));
+    }
+
+    match env::var_os("PATH") {
+        Some(paths) => env::split_paths(&paths).any(|dir| {
+            let path = dir.join(program);
+            readable(&path)
+                || (cfg!(windows) && readable(&path.with_extension("exe")))
+        }),
+        None => false,
+    }
+}
+
+/// Returns the destination directories of all copy tasks.
+fn copy_dirs(rules: &Rules) -> BTreeSet<res::Any> {
+    let mut set = res::Set::new();
+
+    for task in rules.iter().flat_map(|rule| rule.tasks.iter()) {
+        if let task::Any::Copy(copy) = task {
+            copy.known_inputs(&mut set);
+        }
+    }
+
+    set.into_iter()
+        .filter(|r| matches!(r, res::Any::Dir(_)))
+        .collect()
+}
+
+fn missing_dirs(
+    graph: &BuildGraph,
+    dirs: &BTreeSet<res::Any>,
+    root: &Path,
+    problems: &mut Vec<Problem>,
+) {
+    for dir in dirs {
+        let index = match graph.node_to_index(&Node::Resource(dir.clone())) {
+            Some(index) => index,
+            None => continue,
+        };
+
+        let created = graph.incoming(index).any(|(task, _)| {
+            match graph.node_from_index(task) {
+                Node::Task(t) => {
+                    t.tasks.iter().any(|t| matches!(t, task::Any::MakeDir(_)))
+                }
+                Node::Resource(_) | Node::Alias(_) => false,
+            }
+        });
+
+        // A directory that is already part of the source tree is fine.
+        if !created && !root.join(dir.path()).is_dir() {
+            problems.push(Problem::new(
+                Lint::MissingDirectory,
+                format!(
+                    "'{}' is the destination of a copy, but no task creates it",
+                    dir
+                ),
+            ));
+        }
+    }
+}
+
+fn outputs_as_sources(graph: &BuildGraph, problems: &mut Vec<Problem>) {
+    fn kind(r: &res::Any) -> &'static str {
+        match r {
+            res::Any::File(_) => "file",
+            res::Any::Dir(_) => "directory",
+        }
+    }
+
+    let mut outputs = HashMap::new();
+    let mut sources = Vec::new();
+
+    for index in graph.nodes() {
+        if let Node::Resource(r) = graph.node_from_index(index) {
+            if graph.incoming(index).next().is_some() {
+                outputs.insert(r.path(), r);
+            } else {
+                sources.push(r);
+            }
+        }
+    }
+
+    sources.sort();
+
+    for source in sources {
+        if let Some(output) = outputs.get(source.path()) {
+            problems.push(Problem::new(
+                Lint::OutputAsSource,
+                format!(
+                    "'{}' is an input {} that no task creates, but it is also \
+                     the output {} of a task",
+                    source,
+                    kind(source),
+                    kind(output)
+                ),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::tempdir;
+
+    fn lints(data: &str, root: &Path) -> Vec<Lint> {
+        check(Rules::from_str(data).unwrap(), root)
+            .into_iter()
+            .map(|p| p.lint)
+            .collect()
+    }
+
+    #[test]
+    fn test_clean() {
+        let root = tempdir().unwrap();
+        fs::write(root.path().join("tool"), "").unwrap();
+
+        let data = r#"[
+        {
+            "inputs": [{"file": "foo.c"}],
+            "tasks": [{"command": {"program": "./tool", "args": ["foo.c"]}}],
+            "outputs": [{"file": "obj/foo.o"}]
+        },
+        {
+            "tasks": [{"makedir": {"path": "out"}}]
+        },
+        {
+            "tasks": [{"copy": {"from": "obj/foo.o", "to": "out/foo.o"}}]
+        }
+        ]"#;
+
+        assert_eq!(lints(data, root.path()), vec![]);
+    }
+
+    #[test]
+    fn test_problems() {
+        let root = tempdir().unwrap();
+
+        let data = r#"[
+        {
+            "inputs": [{"file": "../foo.c"}, {"file": "/usr/include/stdio.h"}],
+            "tasks": [{"command": {"program": "./missing", "args": []}}],
+            "outputs": [{"file": "foo.o"}]
+        },
+        {
+            "inputs": [{"file": "../foo.c"}, {"file": "/usr/include/stdio.h"}],
+            "tasks": [{"command": {"program": "./missing", "args": []}}],
+            "outputs": [{"file": "foo.o"}]
+        },
+        {
+            "tasks": [{"copy": {"from": "foo.o", "to": "out/foo.o"}}]
+        },
+        {
+            "inputs": [{"dir": "foo.o"}],
+            "tasks": [{"copy": {"from": "bar", "to": "baz"}}]
+        }
+        ]"#;
+
+        let mut lints = lints(data, root.path());
+        lints.sort();
+
+        assert_eq!(
+            lints,
+            vec![
+                Lint::OutputAsSource,
+                Lint::AbsolutePath,
+                Lint::OutsideRoot,
+                Lint::MissingProgram,
+                Lint::MissingDirectory,
+                Lint::DuplicateRule,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_alias() {
+        let root = tempdir().unwrap();
+
+        let data = r#"{
+            "rules": [],
+            "aliases": {"all": [{"alias": "test"}, {"alias": "docs"}], "test": []}
+        }"#;
+
+        assert_eq!(lints(data, root.path()), vec![Lint::UnknownAlias]);
+    }
+
+    #[test]
+    fn test_cycles_and_races() {
+        let root = tempdir().unwrap();
+
+        let data = r#"[
+        {
+            "inputs": [{"file": "a"}],
+            "tasks": [{"copy": {"from": "a", "to": "b"}}]
+        },
+        {
+            "inputs": [{"file": "b"}],
+            "tasks": [{"copy": {"from": "b", "to": "a"}}]
+        },
+        {
+            "tasks": [{"copy": {"from": "c", "to": "b"}}]
+        }
+        ]"#;
+
+        let mut lints = lints(data, root.path());
+        lints.sort();
+
+        assert_eq!(lints, vec![Lint::Cycle, Lint::Race]);
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Generation of a [JSON compilation database][compdb].
+//!
+//! Tools such as clangd and clang-tidy need to know how each source file is
+//! compiled. Every command in the build graph that compiles C or C++ is
+//! converted to an entry in `compile_commands.json`.
+//!
+//! [compdb]: https://clang.llvm.org/docs/JSONCompilationDatabase.html
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::build_graph::{BuildGraph, Edge, Node};
+use crate::detect::Detect;
+use crate::error::{Error, ResultExt};
+use crate::graph::{EdgeIndex, Indexable, Neighbors, NodeIndex, Nodes};
+use crate::res;
+use crate::task;
+
+/// File extensions of source files that get compiled.
+const SOURCE_EXTENSIONS: &[&str] =
+    &["c", "cc", "cpp", "cxx", "c++", "m", "mm", "cu"];
+
+/// File extensions of object files.
+const OBJECT_EXTENSIONS: &[&str] = &["o", "obj"];
+
+/// A single entry in the compilation database.
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct CompileCommand {
+    /// The working directory of the compilation.
+    pub directory: PathBuf,
+
+    /// The compile command, starting with the program. Response files are
+    /// expanded.
+    pub arguments: Vec<String>,
+
+    /// The source file being compiled.
+    pub file: PathBuf,
+
+    /// The object file being created, if it is known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+}
+
+/// Finds all of the compile commands in the build graph. The source file of a
+/// command is taken from the explicit inputs of its task. Paths are made
+/// absolute using `root`.
+pub fn from_graph(
+    graph: &BuildGraph,
+    root: &Path,
+) -> Result<Vec<CompileCommand>, Error> {
+    let mut commands = Vec::new();
+
+    for index in graph.nodes() {
+        let tasks = match graph.node_from_index(index) {
+            Node::Task(t) => &t.tasks,
+            Node::Resource(_) | Node::Alias(_) => continue,
+        };
+
+        let sources = explicit_files(graph, graph.incoming(index))
+            .filter(|path| has_extension(path, SOURCE_EXTENSIONS))
+            .collect::<Vec<_>>();
+
+        if sources.is_empty() {
+            continue;
+        }
+
+        // Only use the output if it's unambiguous.
+        let outputs = explicit_files(graph, graph.outgoing(index))
+            .filter(|path| has_extension(path, OBJECT_EXTENSIONS))
+            .collect::<Vec<_>>();
+        let output = match outputs.as_slice() {
+            [output] if sources.len() == 1 => Some(root.join(output)),
+            _ => None,
+        };
+
+        for task in tasks.iter() {
+            let command = match task {
+                task::Any::Command(command) => command,
+                _ => continue,
+            };
+
+            match command.detect() {
+                Detect::Clang | Detect::Cl => {}
+                Detect::None => continue,
+            }
+
+            let process = command.process();
+
+            let directory = match &process.cwd {
+                Some(cwd) => root.join(cwd),
+                None => root.to_path_buf(),
+            };
+
+            let args = process
+                .args
+                .expand_response_files(&directory)
+                .with_context(|_| {
+                    format!("Failed expanding response files for '{}'", command)
+                })?;
+
+            let arguments: Vec<String> =
+                std::iter::once(process.program.to_string_lossy().into())
+                    .chain(args.iter().map(|arg| String::from(&***arg)))
+                    .collect();
+
+            for source in &sources {
+                commands.push(CompileCommand {
+                    directory: directory.clone(),
+                    arguments: arguments.clone(),
+                    file: root.join(source),
+                    output: output.clone(),
+                });
+            }
+        }
+    }
+
+    commands.sort_by(|a, b| a.file.cmp(&b.file));
+
+    Ok(commands)
+}
+
+/// Returns the paths of the files on the explicit edges.
+fn explicit_files<'a, I>(
+    graph: &'a BuildGraph,
+    neighbors: I,
+) -> impl Iterator<Item = &'a Path> + 'a
+where
+    I: Iterator<Item = (NodeIndex, EdgeIndex)> + 'a,
+{
+    neighbors.filter_map(move |(node, edge)| {
+        match (graph.node_from_index(node), graph.edge_from_index(edge).1) {
+            (Node::Resource(res::Any::File(file)), Edge::Explicit) => {
+                Some(file.path())
+            }
+            _ => None,
+        }
+    })
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .map(|ext| extensions.contains(&ext))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::build_graph::FromRules;
+    use crate::rules::Rules;
+
+    #[test]
+    fn test_from_graph() -> Result<(), Error> {
+        let data = r#"[
+        {
+            "inputs": [{"file": "src/foo.c"}, {"file": "src/foo.h"}],
+            "tasks": [
+                {
+                    "command": {
+                        "program": "gcc",
+                        "args": [
+                            "-c", "src/foo.c", "-o", "foo.o",
+                            "-DGREETING=hello world", "-L$ORIGIN"
+                        ]
+                    }
+                }
+            ],
+            "outputs": [{"file": "foo.o"}]
+        },
+        {
+            "inputs": [{"file": "foo.o"}],
+            "tasks": [
+                {
+                    "command": {
+                        "program": "gcc",
+                        "args": ["foo.o", "-o", "foo"]
+                    }
+                }
+            ],
+            "outputs": [{"file": "foo"}]
+        }
+        ]"#;
+
+        let graph = BuildGraph::from_rules(Rules::from_str(data)?)?;
+        let root = Path::new("/project");
+
+        assert_eq!(
+            from_graph(&graph, root)?,
+            vec![CompileCommand {
+                directory: root.into(),
+                arguments: vec![
+                    "gcc".into(),
+                    "-c".into(),
+                    "src/foo.c".into(),
+                    "-o".into(),
+                    "foo.o".into(),
+                    // Arguments are passed through as is, not quoted.
+                    "-DGREETING=hello world".into(),
+                    "-L$ORIGIN".into(),
+                ],
+                file: root.join("src/foo.c"),
+                output: Some(root.join("foo.o")),
+            }]
+        );
+
+        Ok(())
+    }
+}
@@ -19,18 +19,19 @@
 // THE SOFTWARE.
 
 use std::borrow::Cow;
-use std::io::{self, Read};
+use std::io::Read;
 use std::path::Path;
 
 use crate::error::{Error, ResultExt};
 use crate::util::Process;
 
 use super::detected::Detected;
+use super::diagnostic::Log;
 
 pub fn run(
     root: &Path,
     process: &Process,
-    log: &mut dyn io::Write,
+    log: &mut dyn Log,
 ) -> Result<Detected, Error> {
     let mut process = Cow::Borrowed(process);
 
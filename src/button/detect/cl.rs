@@ -26,13 +26,14 @@ use crate::error::{Error, ResultExt};
 use crate::util::Process;
 
 use super::detected::Detected;
+use super::diagnostic::{parse_msvc, Log};
 
 static INCLUDE_PREFIX: &str = "Note: including file: ";
 
 pub fn run(
     root: &Path,
     process: &Process,
-    log: &mut dyn io::Write,
+    log: &mut dyn Log,
 ) -> Result<Detected, Error> {
     let mut process = process.clone();
 
@@ -100,6 +101,10 @@ pub fn run(
             }
         } else {
             log.write_all(line.as_ref())?;
+
+            if let Some(diagnostic) = parse_msvc(&line) {
+                log.diagnostic(diagnostic);
+            }
         }
 
         line.clear();
@@ -19,7 +19,7 @@
 // THE SOFTWARE.
 
 use std::fs;
-use std::io::{self, Read};
+use std::io::{self, BufRead};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
@@ -27,6 +27,7 @@ use crate::error::{Error, ResultExt};
 use crate::util::Process;
 
 use super::detected::Detected;
+use super::diagnostic::{parse_gcc, Log};
 
 use tempfile::NamedTempFile;
 
@@ -35,7 +36,7 @@ use crate::util::MakeFile;
 pub fn run(
     root: &Path,
     process: &Process,
-    log: &mut dyn io::Write,
+    log: &mut dyn Log,
 ) -> Result<Detected, Error> {
     let mut process = process.clone();
 
@@ -69,17 +70,22 @@ pub fn run(
         None
     };
 
-    let (mut reader, child) = process.spawn(root)?;
+    let (reader, child) = process.spawn(root)?;
 
-    // Read the combined stdout/stderr.
-    let mut buf = [0u8; 4096];
-    loop {
-        let n = reader.read(&mut buf)?;
-        if n == 0 {
-            break;
+    // Read the combined stdout/stderr one line at a time such that
+    // diagnostics can be parsed out of it.
+    let mut reader = io::BufReader::new(reader);
+    let mut line = Vec::new();
+
+    while reader.read_until(b'\n', &mut line)? != 0 {
+        log.write_all(&line)?;
+
+        let text = String::from_utf8_lossy(&line);
+        if let Some(diagnostic) = parse_gcc(&console::strip_ansi_codes(&text)) {
+            log.diagnostic(diagnostic);
         }
 
-        log.write_all(&buf[0..n])?;
+        line.clear();
     }
 
     child.wait()?;
@@ -0,0 +1,371 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Parsing of compiler diagnostics.
+//!
+//! Compilers report errors and warnings as lines of text. These are extracted
+//! into structured records such that they can be consumed without having to
+//! parse the output of the task again.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// The severity of a diagnostic.
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// An error, warning, or note from a compiler.
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
+pub struct Diagnostic {
+    /// The file the diagnostic is about, exactly as the compiler reported it.
+    pub path: PathBuf,
+
+    /// The line number, starting at 1.
+    pub line: Option<u32>,
+
+    /// The column number, starting at 1.
+    pub column: Option<u32>,
+
+    pub severity: Severity,
+
+    /// The warning flag or error code (e.g., `-Wunused-variable` or `C2065`).
+    pub code: Option<String>,
+
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+
+        write!(f, ": {}: {}", self.severity, self.message)?;
+
+        if let Some(code) = &self.code {
+            write!(f, " [{}]", code)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The output of a task. In addition to the raw output, this receives the
+/// diagnostics that were parsed from it.
+pub trait Log: io::Write {
+    /// Called when a diagnostic is found in the output.
+    fn diagnostic(&mut self, _diagnostic: Diagnostic) {}
+}
+
+impl Log for Vec<u8> {}
+
+impl Log for io::Sink {}
+
+/// Parses a GCC or Clang diagnostic of the form
+/// `file:line:column: severity: message [-Wflag]`. The line and column are
+/// optional. ANSI escape codes must already be stripped.
+pub fn parse_gcc(line: &str) -> Option<Diagnostic> {
+    let line = line.trim_end();
+
+    // Find the severity. The file name may contain colons (e.g., a drive
+    // letter on Windows), so search for the severity instead of splitting on
+    // colons.
+    let (index, marker, severity) = [
+        (": fatal error: ", Severity::Error),
+        (": error: ", Severity::Error),
+        (": warning: ", Severity::Warning),
+        (": note: ", Severity::Note),
+    ]
+    .iter()
+    .filter_map(|(marker, severity)| {
+        line.find(marker).map(|i| (i, *marker, *severity))
+    })
+    .min_by_key(|(i, _, _)| *i)?;
+
+    let location = &line[..index];
+    let message = &line[index + marker.len()..];
+
+    // Peel off up to two trailing numbers for the line and column.
+    let mut numbers = Vec::new();
+    let mut path = location;
+    while numbers.len() < 2 {
+        match path.rfind(':') {
+            Some(i) => match path[i + 1..].parse::<u32>() {
+                Ok(n) => {
+                    numbers.push(n);
+                    path = &path[..i];
+                }
+                Err(_) => break,
+            },
+            None => break,
+        }
+    }
+
+    if path.is_empty() {
+        return None;
+    }
+
+    let (line_number, column) = match numbers.as_slice() {
+        [column, line] => (Some(*line), Some(*column)),
+        [line] => (Some(*line), None),
+        _ => (None, None),
+    };
+
+    // Messages from the compiler driver or linker have no location (e.g.,
+    // `clang: error: no input files`). Don't mistake the program for a file.
+    if line_number.is_none() && is_program_name(path) {
+        return None;
+    }
+
+    // Warnings end with the flag that enabled them.
+    let (message, code) = match message.rfind(" [") {
+        Some(i) if message.ends_with(']') => (
+            &message[..i],
+            Some(message[i + 2..message.len() - 1].to_owned()),
+        ),
+        _ => (message, None),
+    };
+
+    Some(Diagnostic {
+        path: PathBuf::from(path),
+        line: line_number,
+        column,
+        severity,
+        code,
+        message: message.to_owned(),
+    })
+}
+
+/// Returns `true` if the path looks like the name of a program rather than a
+/// source file. That is, it has no directory and no extension.
+fn is_program_name(path: &str) -> bool {
+    !path.contains(&['/', '\\'][..]) && Path::new(path).extension().is_none()
+}
+
+/// Parses an MSVC diagnostic of the form
+/// `file(line[,column]): severity code: message`.
+pub fn parse_msvc(line: &str) -> Option<Diagnostic> {
+    let line = line.trim_end();
+
+    let end = line.find("): ")?;
+    let start = line[..end].rfind('(')?;
+
+    let path = line[..start].trim();
+    if path.is_empty() {
+        return None;
+    }
+
+    let mut numbers = line[start + 1..end].split(',').map(str::parse::<u32>);
+    let line_number = Some(numbers.next()?.ok()?);
+    let column = match numbers.next() {
+        Some(column) => Some(column.ok()?),
+        None => None,
+    };
+
+    let rest = &line[end + 3..];
+
+    let (severity, rest) = if let Some(rest) = strip(rest, "fatal error") {
+        (Severity::Error, rest)
+    } else if let Some(rest) = strip(rest, "error") {
+        (Severity::Error, rest)
+    } else if let Some(rest) = strip(rest, "warning") {
+        (Severity::Warning, rest)
+    } else if let Some(rest) = strip(rest, "note") {
+        (Severity::Note, rest)
+    } else {
+        return None;
+    };
+
+    // The code is between the severity and the colon (e.g., "error C2065:").
+    let colon = rest.find(':')?;
+    let code = rest[..colon].trim();
+    let message = rest[colon + 1..].trim();
+
+    Some(Diagnostic {
+        path: PathBuf::from(path),
+        line: line_number,
+        column,
+        severity,
+        code: if code.is_empty() {
+            None
+        } else {
+            Some(code.to_owned())
+        },
+        message: message.to_owned(),
+    })
+}
+
+/// Strips a prefix that must be followed by a space or colon.
+fn strip<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    s.strip_prefix(prefix)
+        .filter(|rest| rest.starts_with(' ') || rest.starts_with(':'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(
+        path: &str,
+        line: Option<u32>,
+        column: Option<u32>,
+        severity: Severity,
+        code: Option<&str>,
+        message: &str,
+    ) -> Diagnostic {
+        Diagnostic {
+            path: path.into(),
+            line,
+            column,
+            severity,
+            code: code.map(String::from),
+            message: message.into(),
+        }
+    }
+
+    #[test]
+    fn test_parse_gcc() {
+        assert_eq!(
+            parse_gcc("src/foo.c:10:5: error: 'x' undeclared\n"),
+            Some(diagnostic(
+                "src/foo.c",
+                Some(10),
+                Some(5),
+                Severity::Error,
+                None,
+                "'x' undeclared"
+            ))
+        );
+
+        assert_eq!(
+            parse_gcc(
+                "foo.c:3:7: warning: unused variable 'y' [-Wunused-variable]"
+            ),
+            Some(diagnostic(
+                "foo.c",
+                Some(3),
+                Some(7),
+                Severity::Warning,
+                Some("-Wunused-variable"),
+                "unused variable 'y'"
+            ))
+        );
+
+        assert_eq!(
+            parse_gcc(r"C:\src\foo.c:1: note: declared here"),
+            Some(diagnostic(
+                r"C:\src\foo.c",
+                Some(1),
+                None,
+                Severity::Note,
+                None,
+                "declared here"
+            ))
+        );
+
+        assert_eq!(
+            parse_gcc("foo.c: fatal error: no such file"),
+            Some(diagnostic(
+                "foo.c",
+                None,
+                None,
+                Severity::Error,
+                None,
+                "no such file"
+            ))
+        );
+
+        assert_eq!(parse_gcc("clang: error: no input files"), None);
+        assert_eq!(parse_gcc("ld: error: undefined symbol: main"), None);
+        assert_eq!(parse_gcc("In file included from foo.c:1:"), None);
+        assert_eq!(parse_gcc("    int x = y;"), None);
+    }
+
+    #[test]
+    fn test_parse_msvc() {
+        assert_eq!(
+            parse_msvc(
+                r"src\foo.c(10): error C2065: 'x': undeclared identifier"
+            ),
+            Some(diagnostic(
+                r"src\foo.c",
+                Some(10),
+                None,
+                Severity::Error,
+                Some("C2065"),
+                "'x': undeclared identifier"
+            ))
+        );
+
+        assert_eq!(
+            parse_msvc("foo.c(3,7): warning C4101: 'y': unreferenced local"),
+            Some(diagnostic(
+                "foo.c",
+                Some(3),
+                Some(7),
+                Severity::Warning,
+                Some("C4101"),
+                "'y': unreferenced local"
+            ))
+        );
+
+        assert_eq!(
+            parse_msvc("foo.h(1): note: see declaration of 'x'"),
+            Some(diagnostic(
+                "foo.h",
+                Some(1),
+                None,
+                Severity::Note,
+                None,
+                "see declaration of 'x'"
+            ))
+        );
+
+        assert_eq!(parse_msvc("foo.c"), None);
+        assert_eq!(
+            parse_msvc("cl : Command line warning D9002 : ignoring option"),
+            None
+        );
+    }
+}
@@ -22,10 +22,11 @@ pub mod base;
 pub mod cl;
 pub mod clang;
 mod detected;
+pub mod diagnostic;
 
 pub use self::detected::Detected;
+pub use self::diagnostic::{Diagnostic, Log};
 
-use std::io;
 use std::path::Path;
 
 use serde::{Deserialize, Serialize};
@@ -110,7 +111,7 @@ impl Detect {
         self,
         root: &Path,
         process: &Process,
-        log: &mut dyn io::Write,
+        log: &mut dyn Log,
     ) -> Result<Detected, Error> {
         match self {
             Detect::Cl => cl::run(root, process, log),
@@ -0,0 +1,244 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+//! Summarizes the differences between two build graphs. This is useful for
+//! seeing the effect of a change to the build rules before running a build.
+
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::build_graph::{Edge, Node, TaskNode};
+use crate::graph::{Algo, Edges, GraphBase, Indexable};
+use crate::res;
+
+/// The changes between an old build graph and a new build graph.
+#[derive(Serialize, Default, Debug, Eq, PartialEq)]
+pub struct Changes {
+    /// Tasks that are only in the new graph.
+    pub added_tasks: Vec<TaskNode>,
+
+    /// Tasks that are only in the old graph.
+    pub removed_tasks: Vec<TaskNode>,
+
+    /// Resources that are only in the new graph.
+    pub added_resources: Vec<res::Any>,
+
+    /// Resources that are only in the old graph.
+    pub removed_resources: Vec<res::Any>,
+
+    /// Aliases that are only in the new graph.
+    pub added_aliases: Vec<String>,
+
+    /// Aliases that are only in the old graph.
+    pub removed_aliases: Vec<String>,
+
+    /// Edges that are only in the new graph.
+    pub added_edges: Vec<(Node, Node)>,
+
+    /// Edges that are only in the old graph.
+    pub removed_edges: Vec<(Node, Node)>,
+}
+
+impl Changes {
+    /// Finds the changes between two graphs. The results are sorted so that
+    /// the output is stable.
+    pub fn new<'a, A, B>(old: &'a A, new: &'a B) -> Changes
+    where
+        A: GraphBase<Node = Node, Edge = Edge>
+            + Algo<'a>
+            + Edges<'a>
+            + Indexable<'a>,
+        B: GraphBase<Node = Node, Edge = Edge>
+            + Algo<'a>
+            + Edges<'a>
+            + Indexable<'a>,
+    {
+        let diff = old.diff(new);
+
+        let mut changes = Changes::default();
+
+        for index in diff.left_only_nodes.iter() {
+            match old.node_from_index(index) {
+                Node::Resource(r) => changes.removed_resources.push(r.clone()),
+                Node::Task(t) => changes.removed_tasks.push(t.clone()),
+                Node::Alias(a) => changes.removed_aliases.push(a.clone()),
+            }
+        }
+
+        for index in diff.right_only_nodes.iter() {
+            match new.node_from_index(index) {
+                Node::Resource(r) => changes.added_resources.push(r.clone()),
+                Node::Task(t) => changes.added_tasks.push(t.clone()),
+                Node::Alias(a) => changes.added_aliases.push(a.clone()),
+            }
+        }
+
+        for index in diff.left_only_edges.iter() {
+            let (a, b) = old.edge_from_index(index).0;
+            changes.removed_edges.push((
+                old.node_from_index(a).clone(),
+                old.node_from_index(b).clone(),
+            ));
+        }
+
+        for index in diff.right_only_edges.iter() {
+            let (a, b) = new.edge_from_index(index).0;
+            changes.added_edges.push((
+                new.node_from_index(a).clone(),
+                new.node_from_index(b).clone(),
+            ));
+        }
+
+        changes.added_tasks.sort();
+        changes.removed_tasks.sort();
+        changes.added_resources.sort();
+        changes.removed_resources.sort();
+        changes.added_aliases.sort();
+        changes.removed_aliases.sort();
+        changes.added_edges.sort();
+        changes.removed_edges.sort();
+
+        changes
+    }
+
+    /// Returns true if there are no changes.
+    pub fn is_empty(&self) -> bool {
+        self.added_tasks.is_empty()
+            && self.removed_tasks.is_empty()
+            && self.added_resources.is_empty()
+            && self.removed_resources.is_empty()
+            && self.added_aliases.is_empty()
+            && self.removed_aliases.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+    }
+}
+
+impl fmt::Display for Changes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.removed_tasks.is_empty() || !self.added_tasks.is_empty() {
+            writeln!(f, "Tasks:")?;
+            for t in &self.removed_tasks {
+                writeln!(f, "  - {}{}", t, Tagged(t))?;
+            }
+            for t in &self.added_tasks {
+                writeln!(f, "  + {}{}", t, Tagged(t))?;
+            }
+        }
+
+        if !self.removed_resources.is_empty()
+            || !self.added_resources.is_empty()
+        {
+            writeln!(f, "Resources:")?;
+            for r in &self.removed_resources {
+                writeln!(f, "  - {}", r)?;
+            }
+            for r in &self.added_resources {
+                writeln!(f, "  + {}", r)?;
+            }
+        }
+
+        if !self.removed_aliases.is_empty() || !self.added_aliases.is_empty() {
+            writeln!(f, "Aliases:")?;
+            for a in &self.removed_aliases {
+                writeln!(f, "  - {}", a)?;
+            }
+            for a in &self.added_aliases {
+                writeln!(f, "  + {}", a)?;
+            }
+        }
+
+        if !self.removed_edges.is_empty() || !self.added_edges.is_empty() {
+            writeln!(f, "Edges:")?;
+            for (a, b) in &self.removed_edges {
+                writeln!(f, "  - {} -> {}", a, b)?;
+            }
+            for (a, b) in &self.added_edges {
+                writeln!(f, "  + {} -> {}", a, b)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Displays the tags of a task, if any. Otherwise, a task whose tags changed
+/// would look the same on both sides of the diff.
+struct Tagged<'a>(&'a TaskNode);
+
+impl<'a> fmt::Display for Tagged<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for tag in &self.0.tags {
+            write!(f, " #{}", tag)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::build_graph::{BuildGraph, FromRules};
+    use crate::res::File;
+    use crate::rules::Rules;
+
+    fn graph(data: &str) -> BuildGraph {
+        BuildGraph::from_rules(Rules::from_str(data).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_changes() {
+        let old = graph(
+            r#"[
+            {
+                "inputs": [{"file": "foo.c"}],
+                "tasks": [{"command": {"program": "cc", "args": ["foo.c"]}}],
+                "outputs": [{"file": "foo"}]
+            }
+            ]"#,
+        );
+
+        let new = graph(
+            r#"[
+            {
+                "inputs": [{"file": "foo.c"}, {"file": "foo.h"}],
+                "tasks": [{"command": {"program": "cc", "args": ["foo.c"]}}],
+                "outputs": [{"file": "foo"}]
+            }
+            ]"#,
+        );
+
+        assert!(Changes::new(&old, &old).is_empty());
+
+        let changes = Changes::new(&old, &new);
+        assert!(changes.added_tasks.is_empty());
+        assert!(changes.removed_tasks.is_empty());
+        assert_eq!(changes.added_resources, vec![File::from("foo.h").into()]);
+        assert!(changes.removed_resources.is_empty());
+        assert_eq!(changes.added_edges.len(), 1);
+        assert!(changes.removed_edges.is_empty());
+
+        let changes = Changes::new(&new, &old);
+        assert_eq!(changes.removed_resources, vec![File::from("foo.h").into()]);
+        assert_eq!(changes.removed_edges.len(), 1);
+    }
+}
@@ -130,6 +130,10 @@ pub enum ErrorKind {
     #[display(fmt = "Failed creating build graph")]
     BuildGraph,
 
+    /// A requested target is not in the build graph.
+    #[display(fmt = "'{}' is not an alias or resource in the build graph", _0)]
+    UnknownTarget(String),
+
     #[display(fmt = "{}", _0)]
     Other(Box<dyn std::error::Error + Send + Sync>),
 }
@@ -18,6 +18,7 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 
+use std::collections::HashMap;
 use std::io::{self, Write};
 use std::thread::{self, JoinHandle};
 use std::time::Duration;
@@ -28,8 +29,11 @@ use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 
 use super::{
     BeginTaskEvent, ChecksumErrorEvent, DeleteEvent, EndBuildEvent,
-    EndTaskEvent, Event, EventHandler, Timestamp,
+    EndTaskEvent, Event, EventHandler, PlanEvent, RecoverStateEvent, SkipEvent,
+    Timestamp,
 };
+use crate::stats::Stats;
+use crate::task;
 
 #[derive(Clone)]
 struct TaskState {
@@ -39,6 +43,9 @@ struct TaskState {
     /// Progress bar associated with this task.
     pb: ProgressBar,
 
+    /// The task being executed.
+    task: Option<task::Any>,
+
     /// String of the task being executed.
     name: String,
 
@@ -51,6 +58,7 @@ impl TaskState {
         TaskState {
             start,
             pb,
+            task: None,
             name: String::new(),
             buf: Vec::new(),
         }
@@ -88,6 +96,16 @@ struct Inner {
 
     // Name of the build.
     name: String,
+
+    // Overall progress of the build. This stays empty until we know which
+    // tasks may get executed.
+    progress: ProgressBar,
+
+    // Estimated durations of planned tasks that haven't finished yet. Empty if
+    // there are no statistics from previous builds. The same task may appear
+    // in the plan more than once, so each task has a duration for every
+    // time it was planned.
+    remaining: HashMap<task::Any, Vec<Duration>>,
 }
 
 impl Inner {
@@ -98,6 +116,9 @@ impl Inner {
 
         let progress = MultiProgress::new();
 
+        let overall = progress.add(ProgressBar::new(0));
+        overall.set_style(ProgressStyle::default_bar().template("{wide_msg}"));
+
         for i in 0..threads {
             let pb = progress.add(ProgressBar::new_spinner());
             pb.set_style(Console::style_idle());
@@ -133,6 +154,8 @@ impl Inner {
             pb_thread,
             tick_thread,
             name,
+            progress: overall,
+            remaining: HashMap::new(),
         }
     }
 
@@ -142,6 +165,8 @@ impl Inner {
                 .finish_with_message(&style("Done").dim().to_string());
         }
 
+        self.progress.finish_and_clear();
+
         self.tick_thread.join().unwrap();
         self.pb_thread.join().unwrap()?;
 
@@ -195,6 +220,7 @@ impl Inner {
         task.pb.set_style(Console::style_running());
         task.pb.set_message(&name);
 
+        task.task = Some(event.task);
         task.name = name;
 
         Ok(())
@@ -230,9 +256,106 @@ impl Inner {
 
         task.pb.set_style(Console::style_idle());
 
+        if let Some(planned) = task.task.take() {
+            self.finish_planned(&planned);
+        }
+
+        self.progress.inc(1);
+        self.update_eta();
+
+        Ok(())
+    }
+
+    pub fn skip(
+        &mut self,
+        _timestamp: Timestamp,
+        event: SkipEvent,
+    ) -> Result<(), io::Error> {
+        for task in &event.tasks {
+            self.finish_planned(task);
+        }
+
+        self.progress.inc(event.tasks.len() as u64);
+        self.update_eta();
+
+        Ok(())
+    }
+
+    /// Removes one planned occurrence of the task from the estimates.
+    fn finish_planned(&mut self, task: &task::Any) {
+        if let Some(estimates) = self.remaining.get_mut(task) {
+            estimates.pop();
+
+            if estimates.is_empty() {
+                self.remaining.remove(task);
+            }
+        }
+    }
+
+    pub fn plan(
+        &mut self,
+        _timestamp: Timestamp,
+        event: PlanEvent,
+        stats: Option<&Stats>,
+    ) -> Result<(), io::Error> {
+        if event.tasks.is_empty() {
+            return Ok(());
+        }
+
+        self.progress.set_length(event.tasks.len() as u64);
+        self.progress.set_style(Console::style_progress());
+
+        if let Some(stats) = stats {
+            let estimates: Vec<_> = event
+                .tasks
+                .iter()
+                .map(|t| stats.estimate(&t.to_string()))
+                .collect();
+
+            // Tasks that have never been executed before are assumed to take
+            // the average amount of time.
+            let known: Vec<_> = estimates.iter().flatten().collect();
+            let fallback = if known.is_empty() {
+                Duration::default()
+            } else {
+                known.iter().cloned().sum::<Duration>() / known.len() as u32
+            };
+
+            self.remaining.clear();
+
+            for (task, estimate) in event.tasks.into_iter().zip(estimates) {
+                self.remaining
+                    .entry(task)
+                    .or_default()
+                    .push(estimate.unwrap_or(fallback));
+            }
+        }
+
+        self.update_eta();
+
         Ok(())
     }
 
+    /// Updates the estimated time remaining based on the tasks that haven't
+    /// finished yet.
+    fn update_eta(&self) {
+        if self.remaining.is_empty() {
+            self.progress.set_message("");
+            return;
+        }
+
+        let remaining: Duration = self.remaining.values().flatten().sum();
+        let eta = remaining / self.tasks.len().max(1) as u32;
+
+        // Don't show sub-second precision. It just adds noise.
+        let eta = Duration::from_secs(eta.as_secs());
+
+        self.progress.set_message(&format!(
+            "ETA {}",
+            style(format_duration(eta)).cyan()
+        ));
+    }
+
     pub fn delete(
         &mut self,
         _timestamp: Timestamp,
@@ -267,6 +390,27 @@ impl Inner {
 
         Ok(())
     }
+
+    pub fn recover_state(
+        &mut self,
+        _timestamp: Timestamp,
+        event: RecoverStateEvent,
+    ) -> Result<(), io::Error> {
+        let recovery = if event.backup {
+            "Using the backup from the previous build instead."
+        } else {
+            "Rebuilding everything from the build rules."
+        };
+
+        self.tasks[0].pb.println(format!(
+            "{}: {}. {}",
+            style("Warning").bold().yellow(),
+            event.error,
+            recovery
+        ));
+
+        Ok(())
+    }
 }
 
 /// Logs events to a console.
@@ -275,6 +419,9 @@ pub struct Console {
     // Delay creation of the inner state until we receive our first BeginBuild
     // event. This lets us handle any number of threads.
     inner: Option<Inner>,
+
+    // Statistics from previous builds. Used to estimate the time remaining.
+    stats: Option<Stats>,
 }
 
 impl Console {
@@ -289,10 +436,23 @@ impl Console {
         ))
     }
 
+    fn style_progress() -> ProgressStyle {
+        ProgressStyle::default_bar()
+            .template("{bar:40.cyan/blue} {pos}/{len} ({percent}%) {wide_msg}")
+            .progress_chars("=> ")
+    }
+
     pub fn new() -> Self {
         // Delay initialization until we receive a BeginBuild event.
         Self::default()
     }
+
+    /// Uses statistics from previous builds to show the estimated time
+    /// remaining.
+    pub fn with_stats(mut self, stats: Stats) -> Self {
+        self.stats = Some(stats);
+        self
+    }
 }
 
 impl EventHandler for Console {
@@ -340,6 +500,25 @@ impl EventHandler for Console {
                     inner.checksum_error(timestamp, event)?;
                 }
             }
+            Event::RecoverState(event) => {
+                if let Some(inner) = &mut self.inner {
+                    inner.recover_state(timestamp, event)?;
+                }
+            }
+            Event::Plan(event) => {
+                if let Some(inner) = &mut self.inner {
+                    inner.plan(timestamp, event, self.stats.as_ref())?;
+                }
+            }
+            Event::Diagnostic(_) => {
+                // Diagnostics are already displayed as part of the task
+                // output.
+            }
+            Event::Skip(event) => {
+                if let Some(inner) = &mut self.inner {
+                    inner.skip(timestamp, event)?;
+                }
+            }
         }
 
         Ok(())
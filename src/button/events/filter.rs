@@ -0,0 +1,490 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+use std::cmp::Ordering;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use derive_more::Display;
+use humantime::parse_duration;
+use regex::Regex;
+
+use super::{Event, EventHandler, Timestamp};
+
+/// A point in time used to filter events.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TimeBound {
+    /// An absolute point in time.
+    Absolute(Timestamp),
+
+    /// An offset from the first event.
+    Offset(Duration),
+}
+
+#[derive(Debug, Display)]
+#[display(
+    fmt = "invalid time '{}': expected a duration (e.g., \"1m 30s\") or an \
+           RFC 3339 timestamp",
+    _0
+)]
+pub struct TimeBoundError(String);
+
+impl std::error::Error for TimeBoundError {}
+
+impl FromStr for TimeBound {
+    type Err = TimeBoundError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(offset) = parse_duration(s) {
+            return Ok(TimeBound::Offset(offset));
+        }
+
+        DateTime::parse_from_rfc3339(s)
+            .map(|t| TimeBound::Absolute(t.with_timezone(&Utc)))
+            .map_err(|_| TimeBoundError(s.to_owned()))
+    }
+}
+
+impl TimeBound {
+    /// Compares a timestamp with this bound. `start` is the timestamp that
+    /// offsets are relative to.
+    fn compare(self, timestamp: Timestamp, start: Timestamp) -> Ordering {
+        match self {
+            TimeBound::Absolute(t) => timestamp.cmp(&t),
+            TimeBound::Offset(offset) => match (timestamp - start).to_std() {
+                Ok(elapsed) => elapsed.cmp(&offset),
+
+                // The timestamp is before the start.
+                Err(_) => Ordering::Less,
+            },
+        }
+    }
+}
+
+/// What to do with the events of a task.
+enum TaskFilter {
+    /// The task is excluded.
+    Skip,
+
+    /// The task is included.
+    Forward,
+
+    /// It isn't known yet if the task is included. Its events are held back
+    /// until it finishes.
+    Buffer(Vec<(Timestamp, Event)>),
+}
+
+/// Passes only the events matching some criteria on to another event handler.
+///
+/// Events that aren't specific to a task (e.g., the beginning and end of a
+/// build) are always passed through.
+pub struct Filter<H> {
+    inner: H,
+
+    /// Only include tasks that failed.
+    failed: bool,
+
+    /// Only include tasks whose name matches this pattern.
+    pattern: Option<Regex>,
+
+    /// Only include tasks that started within this time window.
+    since: Option<TimeBound>,
+    until: Option<TimeBound>,
+
+    /// Only include tasks that were executed on this thread.
+    thread: Option<usize>,
+
+    /// Timestamp of the first event. Time offsets are relative to this.
+    start: Option<Timestamp>,
+
+    /// State of each running task, indexed by thread.
+    tasks: Vec<TaskFilter>,
+}
+
+impl<H> Filter<H> {
+    /// Creates a filter that passes all events through.
+    pub fn new(inner: H) -> Self {
+        Filter {
+            inner,
+            failed: false,
+            pattern: None,
+            since: None,
+            until: None,
+            thread: None,
+            start: None,
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Only include tasks that failed.
+    pub fn with_failed(mut self, failed: bool) -> Self {
+        self.failed = failed;
+        self
+    }
+
+    /// Only include tasks whose name matches the given pattern.
+    pub fn with_pattern(mut self, pattern: Regex) -> Self {
+        self.pattern = Some(pattern);
+        self
+    }
+
+    /// Only include tasks that started at or after the given time.
+    pub fn with_since(mut self, since: TimeBound) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Only include tasks that started at or before the given time.
+    pub fn with_until(mut self, until: TimeBound) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    /// Only include tasks executed on the given thread.
+    pub fn with_thread(mut self, thread: usize) -> Self {
+        self.thread = Some(thread);
+        self
+    }
+
+    /// Returns the inner event handler.
+    pub fn into_inner(self) -> H {
+        self.inner
+    }
+
+    /// Returns `true` if tasks are getting filtered at all.
+    fn filters_tasks(&self) -> bool {
+        self.failed
+            || self.pattern.is_some()
+            || self.since.is_some()
+            || self.until.is_some()
+            || self.thread.is_some()
+    }
+
+    /// Returns `true` if an event on the given thread at the given time is
+    /// included.
+    fn included(&self, id: usize, timestamp: Timestamp) -> bool {
+        let start = self.start.unwrap_or(timestamp);
+
+        if let Some(thread) = self.thread {
+            if thread != id {
+                return false;
+            }
+        }
+
+        if let Some(since) = self.since {
+            if since.compare(timestamp, start) == Ordering::Less {
+                return false;
+            }
+        }
+
+        if let Some(until) = self.until {
+            if until.compare(timestamp, start) == Ordering::Greater {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match &self.pattern {
+            Some(pattern) => pattern.is_match(name),
+            None => true,
+        }
+    }
+
+    fn task(&mut self, id: usize) -> &mut TaskFilter {
+        if self.tasks.len() <= id {
+            self.tasks.resize_with(id + 1, || TaskFilter::Skip);
+        }
+
+        &mut self.tasks[id]
+    }
+}
+
+impl<H> EventHandler for Filter<H>
+where
+    H: EventHandler,
+{
+    type Error = H::Error;
+
+    fn call(
+        &mut self,
+        timestamp: Timestamp,
+        event: Event,
+    ) -> Result<(), Self::Error> {
+        if self.start.is_none() {
+            self.start = Some(timestamp);
+        }
+
+        match event {
+            Event::BeginTask(e) => {
+                let included = self.included(e.id, timestamp)
+                    && self.matches(&e.task.to_string());
+
+                let id = e.id;
+                let event = Event::BeginTask(e);
+
+                let state = if !included {
+                    TaskFilter::Skip
+                } else if self.failed {
+                    TaskFilter::Buffer(vec![(timestamp, event)])
+                } else {
+                    self.inner.call(timestamp, event)?;
+                    TaskFilter::Forward
+                };
+
+                *self.task(id) = state;
+            }
+            Event::TaskOutput(e) => match self.task(e.id) {
+                TaskFilter::Skip => {}
+                TaskFilter::Forward => {
+                    self.inner.call(timestamp, Event::TaskOutput(e))?
+                }
+                TaskFilter::Buffer(events) => {
+                    events.push((timestamp, Event::TaskOutput(e)))
+                }
+            },
+            Event::Diagnostic(e) => match self.task(e.id) {
+                TaskFilter::Skip => {}
+                TaskFilter::Forward => {
+                    self.inner.call(timestamp, Event::Diagnostic(e))?
+                }
+                TaskFilter::Buffer(events) => {
+                    events.push((timestamp, Event::Diagnostic(e)))
+                }
+            },
+            Event::EndTask(e) => {
+                let failed = e.result.is_err();
+
+                match std::mem::replace(self.task(e.id), TaskFilter::Skip) {
+                    TaskFilter::Skip => {}
+                    TaskFilter::Forward => {
+                        self.inner.call(timestamp, Event::EndTask(e))?
+                    }
+                    TaskFilter::Buffer(events) => {
+                        if failed {
+                            for (timestamp, event) in events {
+                                self.inner.call(timestamp, event)?;
+                            }
+
+                            self.inner.call(timestamp, Event::EndTask(e))?;
+                        }
+                    }
+                }
+            }
+            Event::Delete(e) => {
+                if self.included(e.id, timestamp)
+                    && self.matches(&e.resource.to_string())
+                    && (!self.failed || e.result.is_err())
+                {
+                    self.inner.call(timestamp, Event::Delete(e))?;
+                }
+            }
+            Event::ChecksumError(e) => {
+                if self.included(e.id, timestamp)
+                    && self.matches(&e.resource.to_string())
+                {
+                    self.inner.call(timestamp, Event::ChecksumError(e))?;
+                }
+            }
+            Event::Plan(e) => {
+                // The plan doesn't make sense anymore if tasks are excluded.
+                if !self.filters_tasks() {
+                    self.inner.call(timestamp, Event::Plan(e))?;
+                }
+            }
+            Event::Skip(e) => {
+                if !self.filters_tasks() {
+                    self.inner.call(timestamp, Event::Skip(e))?;
+                }
+            }
+            event => self.inner.call(timestamp, event)?,
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Self::Error> {
+        self.inner.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    use chrono::Duration as ChronoDuration;
+
+    use crate::detect::Detected;
+    use crate::events::{
+        BeginBuildEvent, BeginTaskEvent, EndTaskEvent, TaskOutputEvent,
+    };
+    use crate::task::MakeDir;
+
+    /// Collects the names of the tasks that finished.
+    #[derive(Default)]
+    struct Finished {
+        running: Vec<Option<String>>,
+        names: Vec<String>,
+        outputs: usize,
+        builds: usize,
+    }
+
+    impl EventHandler for Finished {
+        type Error = io::Error;
+
+        fn call(
+            &mut self,
+            _timestamp: Timestamp,
+            event: Event,
+        ) -> Result<(), Self::Error> {
+            match event {
+                Event::BeginBuild(_) => self.builds += 1,
+                Event::BeginTask(e) => {
+                    self.running.resize_with(e.id + 1, || None);
+                    self.running[e.id] = Some(e.task.to_string());
+                }
+                Event::TaskOutput(_) => self.outputs += 1,
+                Event::EndTask(e) => {
+                    self.names.push(self.running[e.id].take().unwrap());
+                }
+                _ => {}
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Replays a build with three tasks where the second one fails.
+    fn replay(filter: Filter<Finished>) -> Finished {
+        let mut filter = filter;
+        let start = Utc::now();
+        let secs = ChronoDuration::seconds;
+
+        let mut events: Vec<(Timestamp, Event)> = vec![(
+            start,
+            BeginBuildEvent {
+                threads: 2,
+                name: "build".into(),
+            }
+            .into(),
+        )];
+
+        for (i, name) in ["foo", "bar", "baz"].iter().enumerate() {
+            let id = i % 2;
+            let timestamp = start + secs(i as i64 * 10);
+
+            events.push((
+                timestamp,
+                BeginTaskEvent {
+                    id,
+                    task: MakeDir::new(name.into()).into(),
+                }
+                .into(),
+            ));
+            events.push((
+                timestamp,
+                TaskOutputEvent {
+                    id,
+                    chunk: "output".into(),
+                }
+                .into(),
+            ));
+            events.push((
+                timestamp + secs(1),
+                EndTaskEvent {
+                    id,
+                    result: if i == 1 {
+                        Err("oops".into())
+                    } else {
+                        Ok(Detected::new())
+                    },
+                }
+                .into(),
+            ));
+        }
+
+        for (timestamp, event) in events {
+            filter.call(timestamp, event).unwrap();
+        }
+
+        filter.finish().unwrap();
+        filter.into_inner()
+    }
+
+    fn name(path: &str) -> String {
+        MakeDir::new(path.into()).to_string()
+    }
+
+    #[test]
+    fn test_no_filter() {
+        let finished = replay(Filter::new(Finished::default()));
+        assert_eq!(finished.names, vec![name("foo"), name("bar"), name("baz")]);
+        assert_eq!(finished.outputs, 3);
+        assert_eq!(finished.builds, 1);
+    }
+
+    #[test]
+    fn test_failed() {
+        let finished =
+            replay(Filter::new(Finished::default()).with_failed(true));
+        assert_eq!(finished.names, vec![name("bar")]);
+        assert_eq!(finished.outputs, 1);
+        assert_eq!(finished.builds, 1);
+    }
+
+    #[test]
+    fn test_pattern_and_thread() {
+        let finished = replay(
+            Filter::new(Finished::default())
+                .with_pattern(Regex::new("ba").unwrap()),
+        );
+        assert_eq!(finished.names, vec![name("bar"), name("baz")]);
+
+        let finished = replay(Filter::new(Finished::default()).with_thread(0));
+        assert_eq!(finished.names, vec![name("foo"), name("baz")]);
+    }
+
+    #[test]
+    fn test_time_window() {
+        let finished = replay(
+            Filter::new(Finished::default())
+                .with_since("5s".parse().unwrap())
+                .with_until("15s".parse().unwrap()),
+        );
+        assert_eq!(finished.names, vec![name("bar")]);
+    }
+
+    #[test]
+    fn test_parse_time_bound() {
+        assert_eq!(
+            "1m 30s".parse::<TimeBound>().unwrap(),
+            TimeBound::Offset(Duration::from_secs(90))
+        );
+        assert!(matches!(
+            "2019-01-01T00:00:00Z".parse::<TimeBound>().unwrap(),
+            TimeBound::Absolute(_)
+        ));
+        assert!("yesterday".parse::<TimeBound>().is_err());
+    }
+}
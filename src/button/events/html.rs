@@ -0,0 +1,455 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+use std::io::{self, Write};
+use std::time::Duration;
+
+use humantime::format_duration;
+
+use crate::util::escape_markup;
+
+use super::{
+    BeginBuildEvent, BeginTaskEvent, EndBuildEvent, EndTaskEvent, Event,
+    EventHandler, TaskOutputEvent, Timestamp,
+};
+
+const STYLE: &str = r#"
+body { font-family: sans-serif; margin: 2em; color: #222; }
+h1 { font-size: 1.5em; }
+.ok { color: #2a7a2a; }
+.failed { color: #c62828; }
+.timeline { position: relative; border-left: 1px solid #ccc; }
+.track { position: relative; height: 1.6em; margin: 2px 0; background: #f5f5f5; }
+.track-label { position: absolute; left: -5em; width: 4.5em; text-align: right; font-size: 0.8em; line-height: 2em; color: #666; }
+.span { position: absolute; top: 0; bottom: 0; min-width: 1px; background: #64b5f6; border-right: 1px solid #fff; overflow: hidden; white-space: nowrap; font-size: 0.7em; line-height: 2.3em; }
+.span.failed { background: #e57373; color: #fff; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { padding: 0.3em 0.8em; text-align: left; border-bottom: 1px solid #ddd; }
+th { cursor: pointer; user-select: none; background: #eee; }
+td.num { text-align: right; font-variant-numeric: tabular-nums; }
+tr.failed td { background: #ffebee; }
+details { margin: 0.3em 0; }
+details.failed summary { color: #c62828; font-weight: bold; }
+pre { background: #f5f5f5; padding: 0.5em; overflow-x: auto; }
+pre.error { background: #ffebee; }
+"#;
+
+const SCRIPT: &str = r#"
+document.querySelectorAll("table.sortable th").forEach(function (th, column) {
+  th.addEventListener("click", function () {
+    var table = th.closest("table");
+    var body = table.tBodies[0];
+    var rows = Array.prototype.slice.call(body.rows);
+    var ascending = th.dataset.order !== "asc";
+    th.dataset.order = ascending ? "asc" : "desc";
+    rows.sort(function (a, b) {
+      var x = a.cells[column].dataset.value || a.cells[column].textContent;
+      var y = b.cells[column].dataset.value || b.cells[column].textContent;
+      var c = isNaN(x) || isNaN(y) ? x.localeCompare(y) : x - y;
+      return ascending ? c : -c;
+    });
+    rows.forEach(function (row) { body.appendChild(row); });
+  });
+});
+"#;
+
+/// A task that has finished.
+struct Task {
+    name: String,
+    thread: usize,
+    start: Timestamp,
+    end: Timestamp,
+    output: Vec<u8>,
+    error: Option<String>,
+}
+
+/// A task that has started but not yet finished.
+struct Running {
+    name: String,
+    start: Timestamp,
+    output: Vec<u8>,
+}
+
+/// Writes a self-contained HTML report of the build. It contains a timeline of
+/// the tasks on each thread, a sortable table of task durations, and the
+/// output of each task. Failed tasks are highlighted.
+///
+/// Since the report is a single document, nothing is written until the last
+/// event has been received.
+pub struct Html<W> {
+    writer: W,
+
+    /// Name of the build.
+    name: Option<String>,
+
+    /// Time of the first and last events.
+    start: Option<Timestamp>,
+    end: Option<Timestamp>,
+
+    /// The result of the build, if it finished.
+    result: Option<Result<(), String>>,
+
+    running: Vec<Option<Running>>,
+    tasks: Vec<Task>,
+}
+
+impl<W> Html<W> {
+    pub fn new(writer: W) -> Self {
+        Html {
+            writer,
+            name: None,
+            start: None,
+            end: None,
+            result: None,
+            running: Vec::new(),
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn begin_build(&mut self, event: BeginBuildEvent) {
+        self.name = Some(event.name);
+    }
+
+    fn end_build(&mut self, event: EndBuildEvent) {
+        self.result = Some(event.result);
+    }
+
+    fn begin_task(&mut self, timestamp: Timestamp, event: BeginTaskEvent) {
+        if self.running.len() <= event.id {
+            self.running.resize_with(event.id + 1, || None);
+        }
+
+        self.running[event.id] = Some(Running {
+            name: event.task.to_string(),
+            start: timestamp,
+            output: Vec::new(),
+        });
+    }
+
+    fn task_output(&mut self, event: TaskOutputEvent) {
+        if let Some(Some(running)) = self.running.get_mut(event.id) {
+            running.output.extend_from_slice(&event.chunk);
+        }
+    }
+
+    fn end_task(&mut self, timestamp: Timestamp, event: EndTaskEvent) {
+        let running = match self.running.get_mut(event.id) {
+            Some(running) => running.take(),
+            None => None,
+        };
+
+        if let Some(running) = running {
+            self.tasks.push(Task {
+                name: running.name,
+                thread: event.id,
+                start: running.start,
+                end: timestamp,
+                output: running.output,
+                error: event.result.err(),
+            });
+        }
+    }
+
+    /// Time since the first event.
+    fn offset(&self, timestamp: Timestamp) -> Duration {
+        match self.start {
+            Some(start) => (timestamp - start).to_std().unwrap_or_default(),
+            None => Duration::default(),
+        }
+    }
+
+    fn write_html(&mut self) -> Result<(), io::Error>
+    where
+        W: Write,
+    {
+        let name = self.name.as_deref().unwrap_or("build");
+        let total = self.end.map(|end| self.offset(end)).unwrap_or_default();
+        let failures = self.tasks.iter().filter(|t| t.error.is_some()).count();
+
+        let w = &mut self.writer;
+
+        writeln!(w, "<!DOCTYPE html>")?;
+        writeln!(w, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+        writeln!(w, "<title>Button: {}</title>", escape_markup(name))?;
+        writeln!(w, "<style>{}</style>\n</head>\n<body>", STYLE)?;
+
+        let (class, status) = match &self.result {
+            Some(Ok(())) => ("ok", "Finished".to_owned()),
+            Some(Err(err)) => {
+                ("failed", format!("Failed: {}", escape_markup(err)))
+            }
+            None => ("failed", "Did not finish".to_owned()),
+        };
+
+        writeln!(w, "<h1>{}</h1>", escape_markup(name))?;
+        writeln!(
+            w,
+            "<p><span class=\"{}\">{}</span> in {}. {} task(s) executed, {} \
+             failed.</p>",
+            class,
+            status,
+            millis(total),
+            self.tasks.len(),
+            failures,
+        )?;
+
+        // Timeline of tasks for each thread.
+        writeln!(w, "<h2>Timeline</h2>")?;
+        writeln!(w, "<div class=\"timeline\" style=\"margin-left: 5em\">")?;
+
+        let total_secs = total.as_secs_f64().max(f64::EPSILON);
+        let threads = self.tasks.iter().map(|t| t.thread + 1).max();
+
+        for thread in 0..threads.unwrap_or(0) {
+            writeln!(
+                w,
+                "<div class=\"track\"><span class=\"track-label\">{}</span>",
+                thread + 1
+            )?;
+
+            for task in self.tasks.iter().filter(|t| t.thread == thread) {
+                let start = match self.start {
+                    Some(start) => (task.start - start).to_std(),
+                    None => Ok(Duration::default()),
+                }
+                .unwrap_or_default();
+                let duration =
+                    (task.end - task.start).to_std().unwrap_or_default();
+
+                writeln!(
+                    w,
+                    "<div class=\"span{}\" style=\"left: {:.3}%; width: \
+                     {:.3}%\" title=\"{} ({})\">{}</div>",
+                    if task.error.is_some() { " failed" } else { "" },
+                    100.0 * start.as_secs_f64() / total_secs,
+                    100.0 * duration.as_secs_f64() / total_secs,
+                    escape_markup(&task.name),
+                    millis(duration),
+                    escape_markup(&task.name),
+                )?;
+            }
+
+            writeln!(w, "</div>")?;
+        }
+
+        writeln!(w, "</div>")?;
+
+        // Table of task durations.
+        writeln!(w, "<h2>Tasks</h2>")?;
+        writeln!(w, "<table class=\"sortable\">")?;
+        writeln!(
+            w,
+            "<thead><tr><th>Task</th><th>Thread</th><th>Start</th>\
+             <th>Duration</th><th>Status</th></tr></thead>\n<tbody>"
+        )?;
+
+        let start = self.start;
+
+        for task in &self.tasks {
+            let offset = match start {
+                Some(start) => {
+                    (task.start - start).to_std().unwrap_or_default()
+                }
+                None => Duration::default(),
+            };
+            let duration = (task.end - task.start).to_std().unwrap_or_default();
+
+            writeln!(
+                w,
+                "<tr{}><td>{}</td><td class=\"num\">{}</td>\
+                 <td class=\"num\" data-value=\"{}\">{}</td>\
+                 <td class=\"num\" data-value=\"{}\">{}</td><td>{}</td></tr>",
+                if task.error.is_some() {
+                    " class=\"failed\""
+                } else {
+                    ""
+                },
+                escape_markup(&task.name),
+                task.thread + 1,
+                offset.as_secs_f64(),
+                millis(offset),
+                duration.as_secs_f64(),
+                millis(duration),
+                if task.error.is_some() { "failed" } else { "ok" },
+            )?;
+        }
+
+        writeln!(w, "</tbody>\n</table>")?;
+
+        // Output of each task.
+        writeln!(w, "<h2>Output</h2>")?;
+
+        for task in &self.tasks {
+            if task.output.is_empty() && task.error.is_none() {
+                continue;
+            }
+
+            if task.error.is_some() {
+                writeln!(w, "<details class=\"failed\" open>")?;
+            } else {
+                writeln!(w, "<details>")?;
+            }
+
+            writeln!(w, "<summary>{}</summary>", escape_markup(&task.name))?;
+
+            if !task.output.is_empty() {
+                writeln!(
+                    w,
+                    "<pre>{}</pre>",
+                    escape_markup(&String::from_utf8_lossy(&task.output))
+                )?;
+            }
+
+            if let Some(error) = &task.error {
+                writeln!(
+                    w,
+                    "<pre class=\"error\">{}</pre>",
+                    escape_markup(error)
+                )?;
+            }
+
+            writeln!(w, "</details>")?;
+        }
+
+        writeln!(w, "<script>{}</script>\n</body>\n</html>", SCRIPT)?;
+
+        w.flush()
+    }
+}
+
+impl<W> EventHandler for Html<W>
+where
+    W: Write + Send,
+{
+    type Error = io::Error;
+
+    fn call(
+        &mut self,
+        timestamp: Timestamp,
+        event: Event,
+    ) -> Result<(), Self::Error> {
+        if self.start.is_none() {
+            self.start = Some(timestamp);
+        }
+
+        self.end = Some(timestamp);
+
+        match event {
+            Event::BeginBuild(event) => self.begin_build(event),
+            Event::EndBuild(event) => self.end_build(event),
+            Event::BeginTask(event) => self.begin_task(timestamp, event),
+            Event::TaskOutput(event) => self.task_output(event),
+            Event::EndTask(event) => self.end_task(timestamp, event),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Self::Error> {
+        self.write_html()
+    }
+}
+
+/// Formats a duration with millisecond precision.
+fn millis(duration: Duration) -> String {
+    format_duration(Duration::from_millis(duration.as_millis() as u64))
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::{Duration, Utc};
+
+    use crate::task::MakeDir;
+
+    #[test]
+    fn test_report() -> Result<(), io::Error> {
+        let start = Utc::now();
+        let ms = Duration::milliseconds;
+
+        let mut html = Html::new(Vec::new());
+
+        let events: Vec<(Timestamp, Event)> = vec![
+            (
+                start,
+                BeginBuildEvent {
+                    threads: 2,
+                    name: "build".into(),
+                }
+                .into(),
+            ),
+            (
+                start,
+                BeginTaskEvent {
+                    id: 1,
+                    task: MakeDir::new("<a>".into()).into(),
+                }
+                .into(),
+            ),
+            (
+                start + ms(10),
+                TaskOutputEvent {
+                    id: 1,
+                    chunk: "\x1b[31merror\x1b[0m: a & b".into(),
+                }
+                .into(),
+            ),
+            (
+                start + ms(500),
+                EndTaskEvent {
+                    id: 1,
+                    result: Err("failed".into()),
+                }
+                .into(),
+            ),
+            (
+                start + ms(1000),
+                EndBuildEvent {
+                    result: Err("1 task(s) failed".into()),
+                }
+                .into(),
+            ),
+        ];
+
+        for (timestamp, event) in events {
+            html.call(timestamp, event)?;
+        }
+
+        html.finish()?;
+
+        let html = String::from_utf8(html.into_inner()).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("Failed: 1 task(s) failed"));
+        assert!(html.contains("left: 0.000%; width: 50.000%"));
+        assert!(html.contains("<pre>error: a &amp; b</pre>"));
+        assert!(html.contains("<details class=\"failed\" open>"));
+        assert!(!html.contains("<a>"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+use std::io::{self, Write};
+
+use serde::Serialize;
+use serde_json as json;
+
+use super::{Event, EventHandler, Timestamp};
+
+/// A single line of output.
+#[derive(Serialize)]
+struct Line {
+    timestamp: Timestamp,
+    event: json::Value,
+}
+
+/// Writes an event as a JSON object with a "timestamp" and an "event". Task
+/// output is written as a (lossy) UTF-8 string instead of an array of bytes.
+pub(super) fn to_writer<W>(
+    writer: W,
+    timestamp: Timestamp,
+    event: &Event,
+) -> Result<(), json::Error>
+where
+    W: Write,
+{
+    let mut value = json::to_value(event)?;
+
+    if let Event::TaskOutput(e) = event {
+        value["TaskOutput"]["chunk"] = String::from_utf8_lossy(&e.chunk).into();
+    }
+
+    json::to_writer(
+        writer,
+        &Line {
+            timestamp,
+            event: value,
+        },
+    )
+}
+
+/// Writes each event as a JSON object on its own line. This is meant for
+/// consumption by other programs that want to track the progress of a build.
+pub struct JsonLines<W> {
+    writer: W,
+}
+
+impl<W> JsonLines<W> {
+    pub fn new(writer: W) -> Self {
+        JsonLines { writer }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W> EventHandler for JsonLines<W>
+where
+    W: Write + Send,
+{
+    type Error = io::Error;
+
+    fn call(
+        &mut self,
+        timestamp: Timestamp,
+        event: Event,
+    ) -> Result<(), Self::Error> {
+        to_writer(&mut self.writer, timestamp, &event)?;
+
+        // Flush every line so that consumers see events as they happen.
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+
+    fn finish(&mut self) -> Result<(), Self::Error> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::Utc;
+
+    use crate::events::{BeginBuildEvent, TaskOutputEvent};
+
+    #[test]
+    fn test_lines() {
+        let mut handler = JsonLines::new(Vec::new());
+
+        handler
+            .call(
+                Utc::now(),
+                BeginBuildEvent {
+                    threads: 4,
+                    name: "build".into(),
+                }
+                .into(),
+            )
+            .unwrap();
+        handler
+            .call(
+                Utc::now(),
+                TaskOutputEvent {
+                    id: 0,
+                    chunk: "hello\n".into(),
+                }
+                .into(),
+            )
+            .unwrap();
+
+        let output = String::from_utf8(handler.into_inner()).unwrap();
+        let lines: Vec<json::Value> = output
+            .lines()
+            .map(|line| json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["event"]["BeginBuild"]["threads"], 4);
+        assert_eq!(lines[1]["event"]["TaskOutput"]["chunk"], "hello\n");
+        assert!(lines[1]["timestamp"].is_string());
+    }
+}
@@ -0,0 +1,307 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+use std::io::{self, Write};
+
+use crate::util::escape_markup;
+
+use super::{
+    BeginBuildEvent, BeginTaskEvent, EndTaskEvent, Event, EventHandler,
+    TaskOutputEvent, Timestamp,
+};
+
+/// A task that has finished.
+struct TestCase {
+    name: String,
+    seconds: f64,
+    output: Vec<u8>,
+    failure: Option<String>,
+}
+
+/// A task that has started but not yet finished.
+struct Running {
+    name: String,
+    start: Timestamp,
+    output: Vec<u8>,
+}
+
+/// All the tasks in a single build.
+struct TestSuite {
+    name: String,
+    timestamp: Timestamp,
+    cases: Vec<TestCase>,
+}
+
+/// Writes a JUnit XML report where each task is a test case. Failed tasks are
+/// reported as failures. This lets CI systems display build failures next to
+/// test failures.
+///
+/// Each build in the event stream gets its own test suite. Since the report is
+/// a single XML document, nothing is written until the last event has been
+/// received.
+pub struct JUnit<W> {
+    writer: W,
+    running: Vec<Option<Running>>,
+    suites: Vec<TestSuite>,
+}
+
+impl<W> JUnit<W> {
+    pub fn new(writer: W) -> Self {
+        JUnit {
+            writer,
+            running: Vec::new(),
+            suites: Vec::new(),
+        }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn begin_build(&mut self, timestamp: Timestamp, event: BeginBuildEvent) {
+        self.suites.push(TestSuite {
+            name: event.name,
+            timestamp,
+            cases: Vec::new(),
+        });
+    }
+
+    fn begin_task(&mut self, timestamp: Timestamp, event: BeginTaskEvent) {
+        if self.running.len() <= event.id {
+            self.running.resize_with(event.id + 1, || None);
+        }
+
+        self.running[event.id] = Some(Running {
+            name: event.task.to_string(),
+            start: timestamp,
+            output: Vec::new(),
+        });
+    }
+
+    fn task_output(&mut self, event: TaskOutputEvent) {
+        if let Some(Some(running)) = self.running.get_mut(event.id) {
+            running.output.extend_from_slice(&event.chunk);
+        }
+    }
+
+    fn end_task(&mut self, timestamp: Timestamp, event: EndTaskEvent) {
+        let running = match self.running.get_mut(event.id) {
+            Some(running) => running.take(),
+            None => None,
+        };
+
+        let running = match running {
+            Some(running) => running,
+            None => return,
+        };
+
+        let seconds = (timestamp - running.start)
+            .to_std()
+            .map(|d| d.as_secs_f64())
+            .unwrap_or(0.0);
+
+        // Tasks may be reported without a preceding build (e.g., from a
+        // truncated log).
+        if self.suites.is_empty() {
+            self.suites.push(TestSuite {
+                name: "button".into(),
+                timestamp: running.start,
+                cases: Vec::new(),
+            });
+        }
+
+        self.suites.last_mut().unwrap().cases.push(TestCase {
+            name: running.name,
+            seconds,
+            output: running.output,
+            failure: event.result.err(),
+        });
+    }
+
+    fn write_xml(&mut self) -> Result<(), io::Error>
+    where
+        W: Write,
+    {
+        let w = &mut self.writer;
+
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(w, "<testsuites>")?;
+
+        for suite in &self.suites {
+            let failures =
+                suite.cases.iter().filter(|c| c.failure.is_some()).count();
+            let seconds: f64 = suite.cases.iter().map(|c| c.seconds).sum();
+
+            writeln!(
+                w,
+                r#"  <testsuite name="{}" tests="{}" failures="{}" errors="0" time="{:.3}" timestamp="{}">"#,
+                escape_markup(&suite.name),
+                suite.cases.len(),
+                failures,
+                seconds,
+                suite.timestamp.format("%Y-%m-%dT%H:%M:%S"),
+            )?;
+
+            for case in &suite.cases {
+                writeln!(
+                    w,
+                    r#"    <testcase name="{}" classname="{}" time="{:.3}">"#,
+                    escape_markup(&case.name),
+                    escape_markup(&suite.name),
+                    case.seconds,
+                )?;
+
+                if let Some(failure) = &case.failure {
+                    writeln!(
+                        w,
+                        r#"      <failure message="{}">{}</failure>"#,
+                        escape_markup(failure),
+                        escape_markup(failure),
+                    )?;
+                }
+
+                if !case.output.is_empty() {
+                    writeln!(
+                        w,
+                        "      <system-out>{}</system-out>",
+                        escape_markup(&String::from_utf8_lossy(&case.output)),
+                    )?;
+                }
+
+                writeln!(w, "    </testcase>")?;
+            }
+
+            writeln!(w, "  </testsuite>")?;
+        }
+
+        writeln!(w, "</testsuites>")?;
+
+        w.flush()
+    }
+}
+
+impl<W> EventHandler for JUnit<W>
+where
+    W: Write + Send,
+{
+    type Error = io::Error;
+
+    fn call(
+        &mut self,
+        timestamp: Timestamp,
+        event: Event,
+    ) -> Result<(), Self::Error> {
+        match event {
+            Event::BeginBuild(event) => self.begin_build(timestamp, event),
+            Event::BeginTask(event) => self.begin_task(timestamp, event),
+            Event::TaskOutput(event) => self.task_output(event),
+            Event::EndTask(event) => self.end_task(timestamp, event),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Self::Error> {
+        self.write_xml()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::{Duration, Utc};
+
+    use crate::detect::Detected;
+    use crate::task::MakeDir;
+
+    #[test]
+    fn test_report() {
+        let start = Utc::now();
+        let ms = Duration::milliseconds;
+
+        let mut junit = JUnit::new(Vec::new());
+
+        let events: Vec<(Timestamp, Event)> = vec![
+            (
+                start,
+                BeginBuildEvent {
+                    threads: 2,
+                    name: "build".into(),
+                }
+                .into(),
+            ),
+            (
+                start,
+                BeginTaskEvent {
+                    id: 0,
+                    task: MakeDir::new("a".into()).into(),
+                }
+                .into(),
+            ),
+            (
+                start,
+                BeginTaskEvent {
+                    id: 1,
+                    task: MakeDir::new("b".into()).into(),
+                }
+                .into(),
+            ),
+            (
+                start + ms(1),
+                TaskOutputEvent {
+                    id: 1,
+                    chunk: "x < y\n".into(),
+                }
+                .into(),
+            ),
+            (
+                start + ms(250),
+                EndTaskEvent {
+                    id: 0,
+                    result: Ok(Detected::new()),
+                }
+                .into(),
+            ),
+            (
+                start + ms(500),
+                EndTaskEvent {
+                    id: 1,
+                    result: Err("failed".into()),
+                }
+                .into(),
+            ),
+        ];
+
+        for (timestamp, event) in events {
+            junit.call(timestamp, event).unwrap();
+        }
+
+        junit.finish().unwrap();
+
+        let xml = String::from_utf8(junit.into_inner()).unwrap();
+
+        assert!(xml.contains(r#"tests="2" failures="1""#));
+        assert!(xml.contains(r#"time="0.250""#));
+        assert!(xml.contains(r#"<failure message="failed">failed</failure>"#));
+        assert!(xml.contains("<system-out>x &lt; y\n</system-out>"));
+    }
+}
@@ -0,0 +1,271 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use bincode;
+use console::style;
+use hex;
+use sha2::{self, Digest};
+
+use super::Timestamp;
+use super::{
+    BeginTaskEvent, EndTaskEvent, Event, EventHandler, TaskOutputEvent,
+};
+use crate::task;
+
+/// A task that is currently writing to its log.
+struct Running {
+    name: String,
+    path: PathBuf,
+    file: io::BufWriter<fs::File>,
+}
+
+/// A task that has failed.
+struct Failed {
+    name: String,
+    path: PathBuf,
+    error: String,
+}
+
+/// Writes the output of each task to its own log file. The name of the log
+/// file is derived from a hash of the task, so that the same task always
+/// writes to the same file.
+///
+/// When there are no more events, a summary of the failed tasks is printed to
+/// stderr along with the last few lines of their output. This waits until then
+/// so that it doesn't get mixed up with the progress bars of the console.
+pub struct TaskLogs {
+    /// Directory to write the logs to.
+    dir: PathBuf,
+
+    /// Number of lines of output to show for each failed task.
+    summary_lines: usize,
+
+    running: Vec<Option<Running>>,
+    failed: Vec<Failed>,
+}
+
+impl TaskLogs {
+    pub fn new(dir: PathBuf, summary_lines: usize) -> Self {
+        TaskLogs {
+            dir,
+            summary_lines,
+            running: Vec::new(),
+            failed: Vec::new(),
+        }
+    }
+
+    /// Returns the path to the log file for the given task.
+    pub fn path(dir: &Path, task: &task::Any) -> io::Result<PathBuf> {
+        let bytes = bincode::serialize(task)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+        let hash = sha2::Sha256::digest(&bytes);
+
+        Ok(dir.join(format!("{}.log", hex::encode(&hash[..8]))))
+    }
+
+    fn begin_task(&mut self, event: BeginTaskEvent) -> io::Result<()> {
+        if self.running.len() <= event.id {
+            self.running.resize_with(event.id + 1, || None);
+        }
+
+        fs::create_dir_all(&self.dir)?;
+
+        let path = Self::path(&self.dir, &event.task)?;
+        let file = io::BufWriter::new(fs::File::create(&path)?);
+
+        self.running[event.id] = Some(Running {
+            name: event.task.to_string(),
+            path,
+            file,
+        });
+
+        Ok(())
+    }
+
+    fn task_output(&mut self, event: TaskOutputEvent) -> io::Result<()> {
+        if let Some(Some(running)) = self.running.get_mut(event.id) {
+            running.file.write_all(&event.chunk)?;
+        }
+
+        Ok(())
+    }
+
+    fn end_task(&mut self, event: EndTaskEvent) -> io::Result<()> {
+        let running = match self.running.get_mut(event.id) {
+            Some(running) => running.take(),
+            None => None,
+        };
+
+        if let Some(mut running) = running {
+            running.file.flush()?;
+
+            if let Err(error) = event.result {
+                self.failed.push(Failed {
+                    name: running.name,
+                    path: running.path,
+                    error,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes a summary of all failed tasks.
+    pub fn summary<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        if self.failed.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(
+            writer,
+            "\n{} {} task(s) failed:",
+            style("Summary:").bold(),
+            self.failed.len()
+        )?;
+
+        for failed in &self.failed {
+            writeln!(
+                writer,
+                "\n> {}\n  {} {}\n  {} {}",
+                style(&failed.name).bold().red(),
+                style("error:").red(),
+                failed.error,
+                style("log:").dim(),
+                failed.path.display(),
+            )?;
+
+            if self.summary_lines == 0 {
+                continue;
+            }
+
+            // The log may have been deleted in the meantime. There is nothing
+            // to show in that case.
+            let log = match fs::read(&failed.path) {
+                Ok(log) => log,
+                Err(_) => continue,
+            };
+
+            let log = String::from_utf8_lossy(&log);
+            let lines: Vec<_> = log.lines().collect();
+            let start = lines.len().saturating_sub(self.summary_lines);
+
+            if start > 0 {
+                writeln!(writer, "  {}", style("...").dim())?;
+            }
+
+            for line in &lines[start..] {
+                writeln!(writer, "  {}", line)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl EventHandler for TaskLogs {
+    type Error = io::Error;
+
+    fn call(
+        &mut self,
+        _timestamp: Timestamp,
+        event: Event,
+    ) -> Result<(), Self::Error> {
+        match event {
+            Event::BeginTask(event) => self.begin_task(event),
+            Event::TaskOutput(event) => self.task_output(event),
+            Event::EndTask(event) => self.end_task(event),
+            _ => Ok(()),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), Self::Error> {
+        let stderr = io::stderr();
+        self.summary(&mut stderr.lock())?;
+        self.failed.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    use crate::task::MakeDir;
+
+    #[test]
+    fn test_task_logs() -> io::Result<()> {
+        let tempdir = TempDir::new()?;
+        let dir = tempdir.path().join("logs");
+
+        let task: task::Any = MakeDir::new("a".into()).into();
+        let path = TaskLogs::path(&dir, &task)?;
+
+        let mut logs = TaskLogs::new(dir, 2);
+
+        let events: Vec<Event> = vec![
+            BeginTaskEvent { id: 0, task }.into(),
+            TaskOutputEvent {
+                id: 0,
+                chunk: "one\ntwo\n".into(),
+            }
+            .into(),
+            TaskOutputEvent {
+                id: 0,
+                chunk: "three\n".into(),
+            }
+            .into(),
+            EndTaskEvent {
+                id: 0,
+                result: Err("failed".into()),
+            }
+            .into(),
+        ];
+
+        for event in events {
+            logs.call(Utc::now(), event)?;
+        }
+
+        assert_eq!(fs::read_to_string(&path)?, "one\ntwo\nthree\n");
+
+        let mut summary = Vec::new();
+        logs.summary(&mut summary)?;
+
+        let summary = String::from_utf8(summary).unwrap();
+        let summary = console::strip_ansi_codes(&summary);
+
+        assert!(summary.contains("1 task(s) failed"));
+        assert!(summary.contains(&path.display().to_string()));
+        assert!(summary.contains("two\n  three\n"));
+        assert!(!summary.contains("one"));
+
+        Ok(())
+    }
+}
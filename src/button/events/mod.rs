@@ -33,15 +33,35 @@ use chrono::{DateTime, Utc};
 use derive_more::{Display, From};
 use serde::{Deserialize, Serialize};
 
-use crate::detect::Detected;
+use crate::detect::{Detected, Diagnostic, Log};
 use crate::res;
 use crate::task;
 
 mod binary;
 mod console;
+mod filter;
+mod html;
+mod json;
+mod junit;
+mod logs;
+mod plain;
+#[cfg(unix)]
+mod publish;
+mod stats;
+mod trace;
 
 pub use self::binary::Binary;
 pub use self::console::Console;
+pub use self::filter::{Filter, TimeBound, TimeBoundError};
+pub use self::html::Html;
+pub use self::json::JsonLines;
+pub use self::junit::JUnit;
+pub use self::logs::TaskLogs;
+pub use self::plain::Plain;
+#[cfg(unix)]
+pub use self::publish::{PublishFormat, Publisher};
+pub use self::stats::StatsRecorder;
+pub use self::trace::Trace;
 
 /// A build has begun.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -121,6 +141,45 @@ pub struct ChecksumErrorEvent {
     pub error: String,
 }
 
+/// The build state could not be loaded and had to be recovered.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecoverStateEvent {
+    /// Why the build state could not be loaded.
+    pub error: String,
+
+    /// `true` if the state was recovered from the backup. Otherwise, the state
+    /// was reconstructed from the build rules.
+    pub backup: bool,
+}
+
+/// The tasks that may get executed during the build are known.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlanEvent {
+    /// The tasks that may get executed. Fewer tasks may actually get executed
+    /// if it turns out that their inputs haven't changed.
+    pub tasks: Vec<task::Any>,
+
+    /// The total number of tasks in the build graph.
+    pub total: usize,
+}
+
+/// Planned tasks were skipped because their inputs haven't changed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SkipEvent {
+    /// The tasks that won't get executed.
+    pub tasks: Vec<task::Any>,
+}
+
+/// A diagnostic was found in the output of a task.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DiagnosticEvent {
+    /// The thread this task is getting executed on.
+    pub id: usize,
+
+    /// The error, warning, or note reported by the compiler.
+    pub diagnostic: Diagnostic,
+}
+
 /// A single build event.
 #[derive(Clone, Debug, Serialize, Deserialize, From)]
 pub enum Event {
@@ -144,6 +203,18 @@ pub enum Event {
 
     /// The checksum of a resource failed to compute.
     ChecksumError(ChecksumErrorEvent),
+
+    /// The build state had to be recovered.
+    RecoverState(RecoverStateEvent),
+
+    /// The tasks that may get executed are known.
+    Plan(PlanEvent),
+
+    /// A compiler diagnostic was found in the output of a task.
+    Diagnostic(DiagnosticEvent),
+
+    /// Planned tasks were skipped.
+    Skip(SkipEvent),
 }
 
 pub type Timestamp = DateTime<Utc>;
@@ -313,6 +384,14 @@ where
 pub enum AnyHandlerError {
     Binary(<Binary as EventHandler>::Error),
     Console(<Console as EventHandler>::Error),
+    JsonLines(io::Error),
+    Trace(io::Error),
+    JUnit(io::Error),
+    TaskLogs(io::Error),
+    Stats(io::Error),
+    Plain(io::Error),
+    Html(io::Error),
+    Publisher(io::Error),
 }
 
 impl std::error::Error for AnyHandlerError {}
@@ -321,6 +400,23 @@ impl std::error::Error for AnyHandlerError {}
 pub enum AnyHandler {
     Binary(Binary),
     Console(Console),
+    JsonLines(JsonLines<Box<dyn io::Write + Send>>),
+    Trace(Trace<Box<dyn io::Write + Send>>),
+    JUnit(JUnit<Box<dyn io::Write + Send>>),
+    TaskLogs(TaskLogs),
+    Stats(StatsRecorder),
+    Plain(Plain<Box<dyn io::Write + Send>>),
+    Html(Html<Box<dyn io::Write + Send>>),
+    #[cfg(unix)]
+    #[from(ignore)]
+    Publisher(Publisher),
+}
+
+#[cfg(unix)]
+impl From<Publisher> for AnyHandler {
+    fn from(publisher: Publisher) -> Self {
+        AnyHandler::Publisher(publisher)
+    }
 }
 
 impl EventHandler for AnyHandler {
@@ -338,6 +434,31 @@ impl EventHandler for AnyHandler {
             Self::Console(h) => {
                 h.call(timestamp, event).map_err(AnyHandlerError::Console)
             }
+            Self::JsonLines(h) => {
+                h.call(timestamp, event).map_err(AnyHandlerError::JsonLines)
+            }
+            Self::Trace(h) => {
+                h.call(timestamp, event).map_err(AnyHandlerError::Trace)
+            }
+            Self::JUnit(h) => {
+                h.call(timestamp, event).map_err(AnyHandlerError::JUnit)
+            }
+            Self::TaskLogs(h) => {
+                h.call(timestamp, event).map_err(AnyHandlerError::TaskLogs)
+            }
+            Self::Stats(h) => {
+                h.call(timestamp, event).map_err(AnyHandlerError::Stats)
+            }
+            Self::Plain(h) => {
+                h.call(timestamp, event).map_err(AnyHandlerError::Plain)
+            }
+            Self::Html(h) => {
+                h.call(timestamp, event).map_err(AnyHandlerError::Html)
+            }
+            #[cfg(unix)]
+            Self::Publisher(h) => {
+                h.call(timestamp, event).map_err(AnyHandlerError::Publisher)
+            }
         }
     }
 
@@ -345,6 +466,19 @@ impl EventHandler for AnyHandler {
         match self {
             Self::Binary(h) => h.finish().map_err(AnyHandlerError::Binary),
             Self::Console(h) => h.finish().map_err(AnyHandlerError::Console),
+            Self::JsonLines(h) => {
+                h.finish().map_err(AnyHandlerError::JsonLines)
+            }
+            Self::Trace(h) => h.finish().map_err(AnyHandlerError::Trace),
+            Self::JUnit(h) => h.finish().map_err(AnyHandlerError::JUnit),
+            Self::TaskLogs(h) => h.finish().map_err(AnyHandlerError::TaskLogs),
+            Self::Stats(h) => h.finish().map_err(AnyHandlerError::Stats),
+            Self::Plain(h) => h.finish().map_err(AnyHandlerError::Plain),
+            Self::Html(h) => h.finish().map_err(AnyHandlerError::Html),
+            #[cfg(unix)]
+            Self::Publisher(h) => {
+                h.finish().map_err(AnyHandlerError::Publisher)
+            }
         }
     }
 }
@@ -382,6 +516,20 @@ pub trait EventSink {
     fn checksum_error<E>(&self, id: usize, resource: res::Any, error: &E)
     where
         E: fmt::Display;
+
+    /// Sends a `RecoverStateEvent` to the sink.
+    fn recover_state<E>(&self, error: &E, backup: bool)
+    where
+        E: fmt::Display;
+
+    /// Sends a `PlanEvent` to the sink.
+    fn plan(&self, tasks: Vec<task::Any>, total: usize);
+
+    /// Sends a `DiagnosticEvent` to the sink.
+    fn diagnostic(&self, id: usize, diagnostic: Diagnostic);
+
+    /// Sends a `SkipEvent` to the sink.
+    fn skip(&self, tasks: Vec<task::Any>);
 }
 
 // TODO: Don't unwrap. Log the errors instead.
@@ -472,6 +620,33 @@ impl EventSink for EventSender {
         self.send((Utc::now(), Event::ChecksumError(event)))
             .unwrap();
     }
+
+    fn recover_state<E>(&self, error: &E, backup: bool)
+    where
+        E: fmt::Display,
+    {
+        let event = RecoverStateEvent {
+            error: error.to_string(),
+            backup,
+        };
+
+        self.send((Utc::now(), Event::RecoverState(event))).unwrap();
+    }
+
+    fn plan(&self, tasks: Vec<task::Any>, total: usize) {
+        let event = PlanEvent { tasks, total };
+        self.send((Utc::now(), Event::Plan(event))).unwrap();
+    }
+
+    fn diagnostic(&self, id: usize, diagnostic: Diagnostic) {
+        let event = DiagnosticEvent { id, diagnostic };
+        self.send((Utc::now(), Event::Diagnostic(event))).unwrap();
+    }
+
+    fn skip(&self, tasks: Vec<task::Any>) {
+        let event = SkipEvent { tasks };
+        self.send((Utc::now(), Event::Skip(event))).unwrap();
+    }
 }
 
 impl<'a> EventSink for &'a EventSender {
@@ -561,6 +736,33 @@ impl<'a> EventSink for &'a EventSender {
         self.send((Utc::now(), Event::ChecksumError(event)))
             .unwrap();
     }
+
+    fn recover_state<E>(&self, error: &E, backup: bool)
+    where
+        E: fmt::Display,
+    {
+        let event = RecoverStateEvent {
+            error: error.to_string(),
+            backup,
+        };
+
+        self.send((Utc::now(), Event::RecoverState(event))).unwrap();
+    }
+
+    fn plan(&self, tasks: Vec<task::Any>, total: usize) {
+        let event = PlanEvent { tasks, total };
+        self.send((Utc::now(), Event::Plan(event))).unwrap();
+    }
+
+    fn diagnostic(&self, id: usize, diagnostic: Diagnostic) {
+        let event = DiagnosticEvent { id, diagnostic };
+        self.send((Utc::now(), Event::Diagnostic(event))).unwrap();
+    }
+
+    fn skip(&self, tasks: Vec<task::Any>) {
+        let event = SkipEvent { tasks };
+        self.send((Utc::now(), Event::Skip(event))).unwrap();
+    }
 }
 
 /// Helper for writing task output more ergonomically.
@@ -602,6 +804,15 @@ where
     }
 }
 
+impl<S> Log for TaskOutputWriter<S>
+where
+    S: EventSink,
+{
+    fn diagnostic(&mut self, diagnostic: Diagnostic) {
+        self.sink.diagnostic(self.id, diagnostic);
+    }
+}
+
 /// Helper for creating and destroying an event handler thread that receives
 /// events.
 pub struct EventThread<H>
@@ -661,6 +872,10 @@ mod test {
         pub end_task: usize,
         pub delete: usize,
         pub checksum_error: usize,
+        pub recover_state: usize,
+        pub plan: usize,
+        pub diagnostic: usize,
+        pub skip: usize,
     }
 
     impl EventHandler for Stat {
@@ -693,6 +908,18 @@ mod test {
                 Event::ChecksumError(_) => {
                     self.checksum_error += 1;
                 }
+                Event::RecoverState(_) => {
+                    self.recover_state += 1;
+                }
+                Event::Plan(_) => {
+                    self.plan += 1;
+                }
+                Event::Diagnostic(_) => {
+                    self.diagnostic += 1;
+                }
+                Event::Skip(_) => {
+                    self.skip += 1;
+                }
             }
 
             Ok(())
@@ -721,6 +948,10 @@ mod test {
         assert_eq!(stats.end_task, 0);
         assert_eq!(stats.delete, 0);
         assert_eq!(stats.checksum_error, 0);
+        assert_eq!(stats.recover_state, 0);
+        assert_eq!(stats.plan, 0);
+        assert_eq!(stats.diagnostic, 0);
+        assert_eq!(stats.skip, 0);
 
         Ok(())
     }
@@ -0,0 +1,362 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+use std::io::{self, Write};
+
+use humantime::format_duration;
+
+use super::{
+    BeginBuildEvent, BeginTaskEvent, ChecksumErrorEvent, DeleteEvent,
+    EndBuildEvent, EndTaskEvent, Event, EventHandler, PlanEvent,
+    RecoverStateEvent, SkipEvent, TaskOutputEvent, Timestamp,
+};
+
+/// A task that is currently running.
+struct Running {
+    name: String,
+    start: Timestamp,
+    output: Vec<u8>,
+}
+
+/// Logs events as plain lines of text. This is suitable for when the output is
+/// not a terminal (e.g., in CI logs).
+///
+/// Each task is printed when it finishes along with all of its output so that
+/// the output of concurrent tasks doesn't get interleaved:
+///
+/// ```text
+/// [1/3] clang foo.c
+/// [2/3] clang bar.c
+/// ```
+pub struct Plain<W> {
+    writer: W,
+
+    /// Name of the current build and the time it started.
+    build: Option<(String, Timestamp)>,
+
+    /// Number of tasks that may get executed, if known.
+    total: Option<usize>,
+
+    /// Number of tasks that have finished so far.
+    finished: usize,
+
+    /// Tasks that are currently running, indexed by thread.
+    running: Vec<Option<Running>>,
+}
+
+impl<W> Plain<W> {
+    pub fn new(writer: W) -> Self {
+        Plain {
+            writer,
+            build: None,
+            total: None,
+            finished: 0,
+            running: Vec::new(),
+        }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W> Plain<W>
+where
+    W: Write,
+{
+    fn begin_build(
+        &mut self,
+        timestamp: Timestamp,
+        event: BeginBuildEvent,
+    ) -> Result<(), io::Error> {
+        self.build = Some((event.name, timestamp));
+        self.total = None;
+        self.finished = 0;
+        Ok(())
+    }
+
+    fn end_build(
+        &mut self,
+        timestamp: Timestamp,
+        event: EndBuildEvent,
+    ) -> Result<(), io::Error> {
+        let (name, start) = match self.build.take() {
+            Some(build) => build,
+            None => return Ok(()),
+        };
+
+        let duration = (timestamp - start).to_std().unwrap_or_default();
+        let duration = format_duration(duration);
+
+        match event.result {
+            Ok(()) => {
+                writeln!(self.writer, "Finished {} in {}", name, duration)
+            }
+            Err(err) => writeln!(
+                self.writer,
+                "Failed {} after {}: {}",
+                name, duration, err
+            ),
+        }
+    }
+
+    fn plan(&mut self, event: PlanEvent) -> Result<(), io::Error> {
+        self.total = Some(event.tasks.len());
+        Ok(())
+    }
+
+    fn skip(&mut self, event: SkipEvent) -> Result<(), io::Error> {
+        // Skipped tasks are never printed, so they don't count.
+        if let Some(total) = &mut self.total {
+            *total = total.saturating_sub(event.tasks.len());
+        }
+
+        Ok(())
+    }
+
+    fn begin_task(
+        &mut self,
+        timestamp: Timestamp,
+        event: BeginTaskEvent,
+    ) -> Result<(), io::Error> {
+        if self.running.len() <= event.id {
+            self.running.resize_with(event.id + 1, || None);
+        }
+
+        self.running[event.id] = Some(Running {
+            name: event.task.to_string(),
+            start: timestamp,
+            output: Vec::new(),
+        });
+
+        Ok(())
+    }
+
+    fn task_output(&mut self, event: TaskOutputEvent) -> Result<(), io::Error> {
+        if let Some(Some(running)) = self.running.get_mut(event.id) {
+            running.output.extend_from_slice(&event.chunk);
+        }
+
+        Ok(())
+    }
+
+    fn end_task(
+        &mut self,
+        timestamp: Timestamp,
+        event: EndTaskEvent,
+    ) -> Result<(), io::Error> {
+        let running = match self.running.get_mut(event.id) {
+            Some(running) => running.take(),
+            None => None,
+        };
+
+        let running = match running {
+            Some(running) => running,
+            None => return Ok(()),
+        };
+
+        self.finished += 1;
+
+        match self.total {
+            Some(total) => {
+                write!(self.writer, "[{}/{}] ", self.finished, total)?
+            }
+            None => write!(self.writer, "[{}] ", self.finished)?,
+        }
+
+        if event.result.is_err() {
+            write!(self.writer, "FAILED: ")?;
+        }
+
+        writeln!(self.writer, "{}", running.name)?;
+
+        self.writer.write_all(&running.output)?;
+
+        if !running.output.is_empty() && !running.output.ends_with(b"\n") {
+            writeln!(self.writer)?;
+        }
+
+        if let Err(err) = event.result {
+            let duration =
+                (timestamp - running.start).to_std().unwrap_or_default();
+
+            writeln!(
+                self.writer,
+                "Task failed after {}: {}",
+                format_duration(duration),
+                err
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn delete(&mut self, event: DeleteEvent) -> Result<(), io::Error> {
+        match event.result {
+            Ok(()) => writeln!(self.writer, "Deleted {}", event.resource),
+            Err(err) => writeln!(
+                self.writer,
+                "Failed to delete `{}`: {}",
+                event.resource, err
+            ),
+        }
+    }
+
+    fn checksum_error(
+        &mut self,
+        event: ChecksumErrorEvent,
+    ) -> Result<(), io::Error> {
+        writeln!(
+            self.writer,
+            "Failed to compute checksum for {} ({})",
+            event.resource, event.error
+        )
+    }
+
+    fn recover_state(
+        &mut self,
+        event: RecoverStateEvent,
+    ) -> Result<(), io::Error> {
+        let recovery = if event.backup {
+            "Using the backup from the previous build instead."
+        } else {
+            "Rebuilding everything from the build rules."
+        };
+
+        writeln!(self.writer, "Warning: {}. {}", event.error, recovery)
+    }
+}
+
+impl<W> EventHandler for Plain<W>
+where
+    W: Write + Send,
+{
+    type Error = io::Error;
+
+    fn call(
+        &mut self,
+        timestamp: Timestamp,
+        event: Event,
+    ) -> Result<(), Self::Error> {
+        match event {
+            Event::BeginBuild(event) => self.begin_build(timestamp, event)?,
+            Event::EndBuild(event) => self.end_build(timestamp, event)?,
+            Event::BeginTask(event) => self.begin_task(timestamp, event)?,
+            Event::TaskOutput(event) => self.task_output(event)?,
+            Event::EndTask(event) => self.end_task(timestamp, event)?,
+            Event::Delete(event) => self.delete(event)?,
+            Event::ChecksumError(event) => self.checksum_error(event)?,
+            Event::RecoverState(event) => self.recover_state(event)?,
+            Event::Plan(event) => self.plan(event)?,
+            Event::Skip(event) => self.skip(event)?,
+            Event::Diagnostic(_) => {}
+        }
+
+        // Flush after every event so that the log is up to date if the build
+        // gets killed.
+        self.writer.flush()
+    }
+
+    fn finish(&mut self) -> Result<(), Self::Error> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::Utc;
+
+    use crate::detect::Detected;
+    use crate::task::MakeDir;
+
+    #[test]
+    fn test_plain() -> Result<(), io::Error> {
+        let a = MakeDir::new("a".into());
+        let b = MakeDir::new("b".into());
+        let c = MakeDir::new("c".into());
+
+        let mut plain = Plain::new(Vec::new());
+
+        let events: Vec<Event> = vec![
+            PlanEvent {
+                tasks: vec![
+                    a.clone().into(),
+                    b.clone().into(),
+                    c.clone().into(),
+                ],
+                total: 3,
+            }
+            .into(),
+            BeginTaskEvent {
+                id: 0,
+                task: a.clone().into(),
+            }
+            .into(),
+            BeginTaskEvent {
+                id: 1,
+                task: b.clone().into(),
+            }
+            .into(),
+            SkipEvent {
+                tasks: vec![c.into()],
+            }
+            .into(),
+            TaskOutputEvent {
+                id: 0,
+                chunk: "from a".into(),
+            }
+            .into(),
+            TaskOutputEvent {
+                id: 1,
+                chunk: "from b\n".into(),
+            }
+            .into(),
+            EndTaskEvent {
+                id: 1,
+                result: Err("oops".into()),
+            }
+            .into(),
+            EndTaskEvent {
+                id: 0,
+                result: Ok(Detected::new()),
+            }
+            .into(),
+        ];
+
+        for event in events {
+            plain.call(Utc::now(), event)?;
+        }
+
+        let output = String::from_utf8(plain.into_inner()).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+
+        assert_eq!(lines[0], format!("[1/2] FAILED: {}", b));
+        assert_eq!(lines[1], "from b");
+        assert!(lines[2].starts_with("Task failed after"));
+        assert!(lines[2].ends_with(": oops"));
+        assert_eq!(lines[3], format!("[2/2] {}", a));
+        assert_eq!(lines[4], "from a");
+        assert_eq!(lines.len(), 5);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,431 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use bincode;
+
+use super::{json, Event, EventHandler, Timestamp};
+
+/// How long to wait on a subscriber before giving up on it. This keeps a stuck
+/// subscriber from holding up the end of the build.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Number of frames that can be waiting to be sent to a subscriber. If a
+/// subscriber falls further behind than this, it is dropped.
+const QUEUE_LENGTH: usize = 1024;
+
+/// The encoding of published events.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PublishFormat {
+    /// `(Timestamp, Event)` serialized with bincode.
+    Bincode,
+
+    /// A JSON object with a "timestamp" and an "event".
+    Json,
+}
+
+impl PublishFormat {
+    pub fn variants() -> [&'static str; 2] {
+        ["bincode", "json"]
+    }
+}
+
+impl FromStr for PublishFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bincode" => Ok(PublishFormat::Bincode),
+            "json" => Ok(PublishFormat::Json),
+            _ => Err("invalid publish format"),
+        }
+    }
+}
+
+/// Publishes events to any number of subscribers connected to a Unix domain
+/// socket. Each event is sent as a frame consisting of its length as a
+/// big-endian `u32` followed by the encoded event.
+///
+/// Subscribers are accepted on a separate thread so that they don't have to
+/// wait for the next event. Subscribers that connect in the middle of a build
+/// first receive the events since the build began, except for task output.
+///
+/// Each subscriber has its own thread and queue of frames to write so that a
+/// slow subscriber never holds up the build. Subscribers that disconnect or
+/// can't keep up are dropped.
+pub struct Publisher {
+    path: PathBuf,
+    format: PublishFormat,
+    shared: Arc<Mutex<Shared>>,
+    stop: Arc<AtomicBool>,
+    acceptor: Option<thread::JoinHandle<()>>,
+}
+
+/// State shared between the event thread and the thread accepting
+/// subscribers.
+#[derive(Default)]
+struct Shared {
+    subscribers: Vec<Subscriber>,
+
+    /// Frames since the current build began. Task output is left out so that
+    /// this doesn't grow with the amount of output.
+    history: Vec<u8>,
+}
+
+/// A connected subscriber.
+struct Subscriber {
+    /// Queue of frames for the writer.
+    queue: SyncSender<Arc<Vec<u8>>>,
+
+    /// Thread writing the queued frames to the subscriber.
+    writer: thread::JoinHandle<()>,
+}
+
+impl Subscriber {
+    /// Starts writing to the stream, beginning with `history`.
+    fn new(mut stream: UnixStream, history: Vec<u8>) -> Self {
+        let (queue, frames) = mpsc::sync_channel::<Arc<Vec<u8>>>(QUEUE_LENGTH);
+
+        // There is always room for the first frame.
+        queue.try_send(Arc::new(history)).unwrap();
+
+        let writer = thread::spawn(move || {
+            for frame in frames {
+                if stream.write_all(&frame).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Subscriber { queue, writer }
+    }
+
+    /// Queues a frame. Returns `false` if the subscriber went away or has
+    /// fallen too far behind.
+    fn send(&self, frame: &Arc<Vec<u8>>) -> bool {
+        self.queue.try_send(frame.clone()).is_ok()
+    }
+}
+
+impl Publisher {
+    /// Creates the socket at the given path. If the path already exists (e.g.,
+    /// because a previous build was killed), it is replaced.
+    pub fn bind<P>(path: P, format: PublishFormat) -> io::Result<Publisher>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+
+        match fs::remove_file(&path) {
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {}
+            x => x?,
+        }
+
+        let listener = UnixListener::bind(&path)?;
+
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let acceptor = {
+            let shared = shared.clone();
+            let stop = stop.clone();
+            thread::spawn(move || accept(listener, &shared, &stop))
+        };
+
+        Ok(Publisher {
+            path,
+            format,
+            shared,
+            stop,
+            acceptor: Some(acceptor),
+        })
+    }
+
+    /// Path to the socket.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Encodes an event as a frame.
+    fn encode(
+        &self,
+        timestamp: Timestamp,
+        event: &Event,
+    ) -> Result<Vec<u8>, io::Error> {
+        // Reserve space for the length.
+        let mut frame = vec![0; 4];
+
+        match self.format {
+            PublishFormat::Bincode => {
+                bincode::serialize_into(&mut frame, &(timestamp, event))
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            }
+            PublishFormat::Json => {
+                json::to_writer(&mut frame, timestamp, event)?;
+            }
+        }
+
+        let len = (frame.len() - 4) as u32;
+        frame[..4].copy_from_slice(&len.to_be_bytes());
+
+        Ok(frame)
+    }
+}
+
+/// Accepts subscribers until told to stop.
+fn accept(listener: UnixListener, shared: &Mutex<Shared>, stop: &AtomicBool) {
+    for stream in listener.incoming() {
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+
+        if stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_err() {
+            continue;
+        }
+
+        // Catch up on the current build. This is done while holding the lock
+        // so that no events are missed in between.
+        let mut shared = shared.lock().unwrap();
+        let subscriber = Subscriber::new(stream, shared.history.clone());
+        shared.subscribers.push(subscriber);
+    }
+}
+
+impl EventHandler for Publisher {
+    type Error = io::Error;
+
+    fn call(
+        &mut self,
+        timestamp: Timestamp,
+        event: Event,
+    ) -> Result<(), Self::Error> {
+        let frame = Arc::new(self.encode(timestamp, &event)?);
+
+        let mut shared = self.shared.lock().unwrap();
+
+        match event {
+            Event::BeginBuild(_) => {
+                shared.history.clear();
+                shared.history.extend_from_slice(&frame);
+            }
+            Event::TaskOutput(_) => {}
+            _ => shared.history.extend_from_slice(&frame),
+        }
+
+        // Drop subscribers that went away or can't keep up.
+        shared
+            .subscribers
+            .retain(|subscriber| subscriber.send(&frame));
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Self::Error> {
+        let subscribers =
+            std::mem::take(&mut self.shared.lock().unwrap().subscribers);
+
+        // Let the remaining subscribers receive the rest of the events. A
+        // stuck subscriber only holds this up until its write times out.
+        for Subscriber { queue, writer } in subscribers {
+            drop(queue);
+            let _ = writer.join();
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Publisher {
+    fn drop(&mut self) {
+        // Wake up the acceptor so that it sees it needs to stop.
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = UnixStream::connect(&self.path);
+
+        if let Some(acceptor) = self.acceptor.take() {
+            let _ = acceptor.join();
+        }
+
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Read;
+
+    use chrono::Utc;
+    use serde_json as json;
+    use tempfile::TempDir;
+
+    use crate::events::{BeginBuildEvent, EndBuildEvent, TaskOutputEvent};
+
+    fn read_frame(stream: &mut UnixStream) -> io::Result<Vec<u8>> {
+        let mut len = [0; 4];
+        stream.read_exact(&mut len)?;
+
+        let mut frame = vec![0; u32::from_be_bytes(len) as usize];
+        stream.read_exact(&mut frame)?;
+
+        Ok(frame)
+    }
+
+    #[test]
+    fn test_publish() -> io::Result<()> {
+        let tempdir = TempDir::new()?;
+        let path = tempdir.path().join("events.sock");
+
+        let mut publisher = Publisher::bind(&path, PublishFormat::Bincode)?;
+
+        publisher.call(
+            Utc::now(),
+            BeginBuildEvent {
+                threads: 1,
+                name: "build".into(),
+            }
+            .into(),
+        )?;
+
+        // Output isn't replayed to subscribers that connect later.
+        publisher.call(
+            Utc::now(),
+            TaskOutputEvent {
+                id: 0,
+                chunk: "output".into(),
+            }
+            .into(),
+        )?;
+
+        // Connect in the middle of the build.
+        let mut first = UnixStream::connect(&path)?;
+        publisher.call(Utc::now(), EndBuildEvent { result: Ok(()) }.into())?;
+
+        let (_, event): (Timestamp, Event) =
+            bincode::deserialize(&read_frame(&mut first)?).unwrap();
+        assert!(matches!(event, Event::BeginBuild(_)));
+
+        let (_, event): (Timestamp, Event) =
+            bincode::deserialize(&read_frame(&mut first)?).unwrap();
+        assert!(matches!(event, Event::EndBuild(_)));
+
+        // Disconnected subscribers are dropped once their writer notices.
+        drop(first);
+        for _ in 0..1000 {
+            publisher
+                .call(Utc::now(), EndBuildEvent { result: Ok(()) }.into())?;
+
+            if publisher.shared.lock().unwrap().subscribers.is_empty() {
+                break;
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(publisher.shared.lock().unwrap().subscribers.is_empty());
+
+        drop(publisher);
+        assert!(!path.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_accept_while_idle() -> io::Result<()> {
+        let tempdir = TempDir::new()?;
+        let path = tempdir.path().join("events.sock");
+
+        let mut publisher = Publisher::bind(&path, PublishFormat::Bincode)?;
+        publisher.call(Utc::now(), EndBuildEvent { result: Ok(()) }.into())?;
+
+        // The history is sent without waiting for another event.
+        let mut subscriber = UnixStream::connect(&path)?;
+        subscriber.set_read_timeout(Some(Duration::from_secs(10)))?;
+
+        let (_, event): (Timestamp, Event) =
+            bincode::deserialize(&read_frame(&mut subscriber)?).unwrap();
+        assert!(matches!(event, Event::EndBuild(_)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_slow_subscriber() -> io::Result<()> {
+        let tempdir = TempDir::new()?;
+        let path = tempdir.path().join("events.sock");
+
+        let mut publisher = Publisher::bind(&path, PublishFormat::Bincode)?;
+
+        // Never read from the subscriber.
+        let _subscriber = UnixStream::connect(&path)?;
+        while publisher.shared.lock().unwrap().subscribers.is_empty() {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // The subscriber is dropped once its queue fills up instead of
+        // holding up the events.
+        let chunk = vec![b'x'; 4096];
+        for _ in 0..QUEUE_LENGTH * 2 {
+            publisher.call(
+                Utc::now(),
+                TaskOutputEvent {
+                    id: 0,
+                    chunk: chunk.clone().into(),
+                }
+                .into(),
+            )?;
+        }
+
+        assert!(publisher.shared.lock().unwrap().subscribers.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_publish_json() -> io::Result<()> {
+        let tempdir = TempDir::new()?;
+        let path = tempdir.path().join("events.sock");
+
+        let mut publisher = Publisher::bind(&path, PublishFormat::Json)?;
+        let mut subscriber = UnixStream::connect(&path)?;
+
+        publisher.call(Utc::now(), EndBuildEvent { result: Ok(()) }.into())?;
+
+        let value: json::Value =
+            json::from_slice(&read_frame(&mut subscriber)?).unwrap();
+        assert!(value["timestamp"].is_string());
+        assert!(value["event"]["EndBuild"].is_object());
+
+        Ok(())
+    }
+}
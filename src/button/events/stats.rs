@@ -0,0 +1,249 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+use std::io;
+use std::path::PathBuf;
+
+use super::{
+    BeginBuildEvent, BeginTaskEvent, EndBuildEvent, EndTaskEvent, Event,
+    EventHandler, PlanEvent, Timestamp,
+};
+use crate::build::REGENERATE;
+use crate::stats::{BuildStats, Stats};
+
+/// A build that is in progress.
+struct Current {
+    name: String,
+    start: Timestamp,
+
+    /// Total number of tasks in the build graph.
+    total: usize,
+
+    executed: usize,
+    failed: usize,
+}
+
+/// Records task durations and build summaries to a statistics file. The file
+/// is updated at the end of each build. Regenerating the build rules is part of
+/// the build that follows it, so it isn't recorded.
+pub struct StatsRecorder {
+    path: PathBuf,
+    stats: Stats,
+    current: Option<Current>,
+
+    /// Tasks that are currently running, indexed by thread.
+    running: Vec<Option<(String, Timestamp)>>,
+}
+
+impl StatsRecorder {
+    /// Creates a new recorder that adds to the given statistics. The
+    /// statistics are written to `path` at the end of each build.
+    pub fn new(path: PathBuf, stats: Stats) -> Self {
+        StatsRecorder {
+            path,
+            stats,
+            current: None,
+            running: Vec::new(),
+        }
+    }
+
+    /// Returns the statistics recorded so far.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    fn begin_build(&mut self, timestamp: Timestamp, event: BeginBuildEvent) {
+        if event.name == REGENERATE {
+            self.current = None;
+            return;
+        }
+
+        self.current = Some(Current {
+            name: event.name,
+            start: timestamp,
+            total: 0,
+            executed: 0,
+            failed: 0,
+        });
+    }
+
+    fn plan(&mut self, event: PlanEvent) {
+        if let Some(current) = &mut self.current {
+            current.total = event.total;
+        }
+    }
+
+    fn begin_task(&mut self, timestamp: Timestamp, event: BeginTaskEvent) {
+        if self.current.is_none() {
+            return;
+        }
+
+        if self.running.len() <= event.id {
+            self.running.resize_with(event.id + 1, || None);
+        }
+
+        self.running[event.id] = Some((event.task.to_string(), timestamp));
+    }
+
+    fn end_task(&mut self, timestamp: Timestamp, event: EndTaskEvent) {
+        let running = match self.running.get_mut(event.id) {
+            Some(running) => running.take(),
+            None => None,
+        };
+
+        if let Some((name, start)) = running {
+            let duration = (timestamp - start).to_std().unwrap_or_default();
+            let success = event.result.is_ok();
+
+            self.stats.add_task(name, start, duration, success);
+
+            if let Some(current) = &mut self.current {
+                current.executed += 1;
+
+                if !success {
+                    current.failed += 1;
+                }
+            }
+        }
+    }
+
+    fn end_build(
+        &mut self,
+        timestamp: Timestamp,
+        event: EndBuildEvent,
+    ) -> Result<(), io::Error> {
+        let current = match self.current.take() {
+            Some(current) => current,
+            None => return Ok(()),
+        };
+
+        self.stats.add_build(BuildStats {
+            duration: (timestamp - current.start).to_std().unwrap_or_default(),
+            name: current.name,
+            timestamp: current.start,
+            executed: current.executed,
+            cached: current.total.saturating_sub(current.executed),
+            failed: current.failed,
+            success: event.result.is_ok(),
+        });
+
+        self.stats.write_to_path(&self.path).map_err(|err| {
+            io::Error::new(io::ErrorKind::Other, err.to_string())
+        })
+    }
+}
+
+impl EventHandler for StatsRecorder {
+    type Error = io::Error;
+
+    fn call(
+        &mut self,
+        timestamp: Timestamp,
+        event: Event,
+    ) -> Result<(), Self::Error> {
+        match event {
+            Event::BeginBuild(event) => self.begin_build(timestamp, event),
+            Event::Plan(event) => self.plan(event),
+            Event::BeginTask(event) => self.begin_task(timestamp, event),
+            Event::EndTask(event) => self.end_task(timestamp, event),
+            Event::EndBuild(event) => return self.end_build(timestamp, event),
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::{Duration, Utc};
+    use tempfile::TempDir;
+
+    use crate::detect::Detected;
+    use crate::error::Error;
+    use crate::task::MakeDir;
+
+    #[test]
+    fn test_recorder() -> Result<(), Error> {
+        let tempdir = TempDir::new()?;
+        let path = tempdir.path().join("stats");
+
+        let start = Utc::now();
+        let ms = Duration::milliseconds;
+
+        let mut recorder = StatsRecorder::new(path.clone(), Stats::default());
+
+        let events: Vec<(Timestamp, Event)> = vec![
+            (
+                start,
+                BeginBuildEvent {
+                    threads: 1,
+                    name: "build".into(),
+                }
+                .into(),
+            ),
+            (
+                start,
+                PlanEvent {
+                    tasks: vec![MakeDir::new("a".into()).into()],
+                    total: 3,
+                }
+                .into(),
+            ),
+            (
+                start,
+                BeginTaskEvent {
+                    id: 0,
+                    task: MakeDir::new("a".into()).into(),
+                }
+                .into(),
+            ),
+            (
+                start + ms(20),
+                EndTaskEvent {
+                    id: 0,
+                    result: Ok(Detected::new()),
+                }
+                .into(),
+            ),
+            (start + ms(30), EndBuildEvent { result: Ok(()) }.into()),
+        ];
+
+        for (timestamp, event) in events {
+            recorder.call(timestamp, event)?;
+        }
+
+        let stats = Stats::from_path(&path)?;
+
+        let build = stats.builds().next().unwrap();
+        assert_eq!(build.executed, 1);
+        assert_eq!(build.cached, 2);
+        assert!(build.success);
+
+        let name = MakeDir::new("a".into()).to_string();
+        assert_eq!(
+            stats.estimate(&name),
+            Some(std::time::Duration::from_millis(20))
+        );
+
+        Ok(())
+    }
+}
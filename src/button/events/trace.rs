@@ -0,0 +1,431 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+
+use serde::Serialize;
+use serde_json as json;
+
+use super::{
+    BeginBuildEvent, BeginTaskEvent, EndTaskEvent, Event, EventHandler,
+    Timestamp,
+};
+
+/// An event in the Chrome Trace Event Format.
+#[derive(Serialize)]
+struct TraceEvent {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cat: Option<&'static str>,
+    ph: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ts: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<i64>,
+    pid: u32,
+    tid: usize,
+    #[serde(skip_serializing_if = "json::Map::is_empty")]
+    args: json::Map<String, json::Value>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TraceFile {
+    trace_events: Vec<TraceEvent>,
+    display_time_unit: &'static str,
+}
+
+/// A task that has started but not yet finished.
+struct Running {
+    name: String,
+    start: Timestamp,
+
+    /// The build the task is part of.
+    pid: u32,
+}
+
+/// Converts task events into the Chrome Trace Event Format. A single run may
+/// include more than one build (e.g., when the rules are regenerated first).
+/// Each build is shown as its own process and each worker thread gets its own
+/// track within it. The resulting file can be opened in `chrome://tracing` or
+/// in Perfetto.
+///
+/// Since the trace is a single JSON document, nothing is written until the
+/// last event has been received.
+pub struct Trace<W> {
+    writer: W,
+
+    /// The timestamp of the first event. All times are relative to this.
+    start: Option<Timestamp>,
+
+    /// The timestamp of the last event.
+    last: Option<Timestamp>,
+
+    /// Tasks that are currently running, indexed by thread.
+    running: Vec<Option<Running>>,
+
+    /// Names of the builds that have begun. The process ID of a build is its
+    /// position in this list, starting at 1.
+    builds: Vec<String>,
+
+    /// Threads that have run at least one task, by process ID.
+    threads: BTreeSet<(u32, usize)>,
+
+    events: Vec<TraceEvent>,
+}
+
+impl<W> Trace<W> {
+    pub fn new(writer: W) -> Self {
+        Trace {
+            writer,
+            start: None,
+            last: None,
+            running: Vec::new(),
+            builds: Vec::new(),
+            threads: BTreeSet::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Microseconds since the first event.
+    fn micros(&self, timestamp: Timestamp) -> i64 {
+        let start = self.start.unwrap_or(timestamp);
+        (timestamp - start).num_microseconds().unwrap_or(0)
+    }
+
+    /// The process ID of the current build. Tasks outside of any build are
+    /// attributed to the first one.
+    fn pid(&self) -> u32 {
+        self.builds.len().max(1) as u32
+    }
+
+    fn begin_build(&mut self, event: BeginBuildEvent) {
+        self.builds.push(event.name);
+    }
+
+    fn begin_task(&mut self, timestamp: Timestamp, event: BeginTaskEvent) {
+        if self.running.len() <= event.id {
+            self.running.resize_with(event.id + 1, || None);
+        }
+
+        let pid = self.pid();
+
+        self.threads.insert((pid, event.id));
+        self.running[event.id] = Some(Running {
+            name: event.task.to_string(),
+            start: timestamp,
+            pid,
+        });
+    }
+
+    fn end_task(&mut self, timestamp: Timestamp, event: EndTaskEvent) {
+        let running = match self.running.get_mut(event.id) {
+            Some(running) => running.take(),
+            None => None,
+        };
+
+        if let Some(running) = running {
+            let mut args = json::Map::new();
+
+            if let Err(err) = event.result {
+                args.insert("error".into(), err.into());
+            }
+
+            self.complete(event.id, running, timestamp, args);
+        }
+    }
+
+    /// Adds a complete event for a task.
+    fn complete(
+        &mut self,
+        id: usize,
+        running: Running,
+        end: Timestamp,
+        args: json::Map<String, json::Value>,
+    ) {
+        let ts = self.micros(running.start);
+        let dur = self.micros(end) - ts;
+
+        self.events.push(TraceEvent {
+            name: running.name,
+            cat: Some("task"),
+            ph: "X",
+            ts: Some(ts),
+            dur: Some(dur),
+            pid: running.pid,
+            tid: id,
+            args,
+        });
+    }
+}
+
+impl<W> EventHandler for Trace<W>
+where
+    W: Write + Send,
+{
+    type Error = io::Error;
+
+    fn call(
+        &mut self,
+        timestamp: Timestamp,
+        event: Event,
+    ) -> Result<(), Self::Error> {
+        if self.start.is_none() {
+            self.start = Some(timestamp);
+        }
+
+        self.last = Some(timestamp);
+
+        match event {
+            Event::BeginBuild(event) => self.begin_build(event),
+            Event::BeginTask(event) => self.begin_task(timestamp, event),
+            Event::EndTask(event) => self.end_task(timestamp, event),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Self::Error> {
+        // Tasks that never finished (e.g., because the build was interrupted)
+        // are cut off at the last event.
+        if let Some(last) = self.last {
+            let running: Vec<_> = self
+                .running
+                .iter_mut()
+                .enumerate()
+                .filter_map(|(id, running)| running.take().map(|r| (id, r)))
+                .collect();
+
+            for (id, running) in running {
+                let mut args = json::Map::new();
+                args.insert("unfinished".into(), true.into());
+                self.complete(id, running, last, args);
+            }
+        }
+
+        let processes = self.builds.iter().enumerate().map(|(i, name)| {
+            let mut args = json::Map::new();
+            args.insert("name".into(), name.clone().into());
+
+            TraceEvent {
+                name: "process_name".into(),
+                cat: None,
+                ph: "M",
+                ts: None,
+                dur: None,
+                pid: i as u32 + 1,
+                tid: 0,
+                args,
+            }
+        });
+
+        let threads = self.threads.iter().map(|&(pid, id)| {
+            let mut args = json::Map::new();
+            args.insert("name".into(), format!("worker {}", id).into());
+
+            TraceEvent {
+                name: "thread_name".into(),
+                cat: None,
+                ph: "M",
+                ts: None,
+                dur: None,
+                pid,
+                tid: id,
+                args,
+            }
+        });
+
+        let mut trace_events: Vec<_> = processes.chain(threads).collect();
+
+        trace_events.append(&mut self.events);
+
+        json::to_writer(
+            &mut self.writer,
+            &TraceFile {
+                trace_events,
+                display_time_unit: "ms",
+            },
+        )?;
+
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::{Duration, Utc};
+
+    use crate::detect::Detected;
+    use crate::task::{self, MakeDir};
+
+    fn task(path: &str) -> task::Any {
+        MakeDir::new(path.into()).into()
+    }
+
+    #[test]
+    fn test_trace() {
+        let start = Utc::now();
+        let ms = Duration::milliseconds;
+
+        let mut trace = Trace::new(Vec::new());
+
+        let events: Vec<(Timestamp, Event)> = vec![
+            (
+                start,
+                BeginTaskEvent {
+                    id: 0,
+                    task: task("a"),
+                }
+                .into(),
+            ),
+            (
+                start + ms(1),
+                BeginTaskEvent {
+                    id: 1,
+                    task: task("b"),
+                }
+                .into(),
+            ),
+            (
+                start + ms(5),
+                EndTaskEvent {
+                    id: 0,
+                    result: Ok(Detected::new()),
+                }
+                .into(),
+            ),
+            (
+                start + ms(7),
+                EndTaskEvent {
+                    id: 1,
+                    result: Err("oops".into()),
+                }
+                .into(),
+            ),
+        ];
+
+        for (timestamp, event) in events {
+            trace.call(timestamp, event).unwrap();
+        }
+
+        trace.finish().unwrap();
+
+        let value: json::Value = json::from_slice(&trace.into_inner()).unwrap();
+        let events = value["traceEvents"].as_array().unwrap();
+
+        let metadata: Vec<_> =
+            events.iter().filter(|e| e["ph"] == "M").collect();
+        assert_eq!(metadata.len(), 2);
+
+        let complete: Vec<_> =
+            events.iter().filter(|e| e["ph"] == "X").collect();
+        assert_eq!(complete.len(), 2);
+
+        assert_eq!(complete[0]["tid"], 0);
+        assert_eq!(complete[0]["ts"], 0);
+        assert_eq!(complete[0]["dur"], 5000);
+
+        assert_eq!(complete[1]["tid"], 1);
+        assert_eq!(complete[1]["ts"], 1000);
+        assert_eq!(complete[1]["dur"], 6000);
+        assert_eq!(complete[1]["args"]["error"], "oops");
+    }
+
+    #[test]
+    fn test_trace_builds() {
+        let start = Utc::now();
+        let ms = Duration::milliseconds;
+
+        let mut trace = Trace::new(Vec::new());
+
+        // The rules are regenerated before the build itself.
+        let mut events: Vec<(Timestamp, Event)> = Vec::new();
+        for (i, name) in ["regenerate", "build"].iter().enumerate() {
+            let begin = start + ms(10 * i as i64);
+
+            events.push((
+                begin,
+                BeginBuildEvent {
+                    threads: 1,
+                    name: (*name).into(),
+                }
+                .into(),
+            ));
+            events.push((
+                begin,
+                BeginTaskEvent {
+                    id: 0,
+                    task: task(name),
+                }
+                .into(),
+            ));
+            events.push((
+                begin + ms(1),
+                EndTaskEvent {
+                    id: 0,
+                    result: Ok(Detected::new()),
+                }
+                .into(),
+            ));
+        }
+
+        for (timestamp, event) in events {
+            trace.call(timestamp, event).unwrap();
+        }
+
+        trace.finish().unwrap();
+
+        let value: json::Value = json::from_slice(&trace.into_inner()).unwrap();
+        let events = value["traceEvents"].as_array().unwrap();
+
+        let processes: Vec<_> = events
+            .iter()
+            .filter(|e| e["name"] == "process_name")
+            .map(|e| (e["pid"].clone(), e["args"]["name"].clone()))
+            .collect();
+        assert_eq!(
+            processes,
+            vec![(1.into(), "regenerate".into()), (2.into(), "build".into())]
+        );
+
+        let threads: Vec<_> = events
+            .iter()
+            .filter(|e| e["name"] == "thread_name")
+            .map(|e| e["pid"].clone())
+            .collect();
+        assert_eq!(threads, vec![json::Value::from(1), 2.into()]);
+
+        let complete: Vec<_> =
+            events.iter().filter(|e| e["ph"] == "X").collect();
+        assert_eq!(complete.len(), 2);
+        assert_eq!(complete[0]["pid"], 1);
+        assert_eq!(complete[1]["pid"], 2);
+        assert_eq!(complete[1]["ts"], 10000);
+    }
+}
@@ -0,0 +1,394 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Export of the build graph to other build systems.
+//!
+//! This is useful for debugging and for building in environments where button
+//! isn't available. Every task is lowered to an equivalent shell command.
+//!
+//! Inputs that don't exist and aren't produced by any task (e.g., programs
+//! that are found on the `PATH`) are left out, since make and ninja would
+//! refuse to build without them. Aliases become phony targets.
+
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+
+use crate::build_graph::{BuildGraph, Edge, Node};
+use crate::error::Error;
+use crate::graph::{Indexable, Neighbors, NodeIndex, Nodes};
+use crate::task::Task;
+
+/// A task with its inputs and outputs.
+struct Step {
+    inputs: Vec<String>,
+    implicit_inputs: Vec<String>,
+    outputs: Vec<String>,
+    command: String,
+    description: String,
+
+    /// True if the task has no outputs and a name was made up for it.
+    phony: bool,
+
+    /// True if the task generates the build rules.
+    generator: bool,
+}
+
+/// A named group of targets.
+struct Alias {
+    name: String,
+    targets: Vec<String>,
+}
+
+/// Returns true if the node is an input that doesn't exist and that nothing
+/// produces.
+fn is_missing(graph: &BuildGraph, root: &Path, index: NodeIndex) -> bool {
+    graph.incoming(index).next().is_none()
+        && !root.join(graph.node_from_index(index).label()).exists()
+}
+
+fn steps(graph: &BuildGraph, root: &Path) -> Result<Vec<Step>, Error> {
+    let mut steps = Vec::new();
+
+    for index in graph.nodes() {
+        let (task, generator) = match graph.node_from_index(index) {
+            Node::Task(task) => (&task.tasks, task.generator),
+            Node::Resource(_) | Node::Alias(_) => continue,
+        };
+
+        let command = task.shell_command().ok_or_else(|| {
+            failure::format_err!(
+                "Task '{}' cannot be expressed as a shell command",
+                task
+            )
+        })?;
+
+        let mut inputs = Vec::new();
+        let mut implicit_inputs = Vec::new();
+
+        for (node, edge) in graph.incoming(index) {
+            if is_missing(graph, root, node) {
+                continue;
+            }
+
+            let input = graph.node_from_index(node).as_res().to_string();
+
+            match graph.edge_from_index(edge).1 {
+                Edge::Explicit => inputs.push(input),
+                Edge::Implicit => implicit_inputs.push(input),
+            }
+        }
+
+        let mut outputs: Vec<String> = graph
+            .outgoing(index)
+            .map(|(node, _)| graph.node_from_index(node).as_res().to_string())
+            .collect();
+
+        let phony = outputs.is_empty();
+        if phony {
+            outputs.push(format!("button-task-{}", steps.len()));
+        }
+
+        inputs.sort();
+        implicit_inputs.sort();
+        outputs.sort();
+
+        steps.push(Step {
+            inputs,
+            implicit_inputs,
+            outputs,
+            command,
+            description: task.to_string(),
+            phony,
+            generator,
+        });
+    }
+
+    Ok(steps)
+}
+
+fn aliases(graph: &BuildGraph, root: &Path) -> Vec<Alias> {
+    let mut aliases = Vec::new();
+
+    for index in graph.nodes() {
+        if let Node::Alias(name) = graph.node_from_index(index) {
+            let mut targets: Vec<String> = graph
+                .incoming(index)
+                .filter(|(node, _)| !is_missing(graph, root, *node))
+                .map(|(node, _)| graph.node_from_index(node).label())
+                .collect();
+
+            targets.sort();
+
+            aliases.push(Alias {
+                name: name.clone(),
+                targets,
+            });
+        }
+    }
+
+    aliases.sort_by(|a, b| a.name.cmp(&b.name));
+    aliases
+}
+
+/// Writes the build graph as a `build.ninja` file. `root` is the directory
+/// of the build rules.
+pub fn to_ninja(
+    graph: &BuildGraph,
+    root: &Path,
+    writer: &mut dyn io::Write,
+) -> Result<(), Error> {
+    let steps = steps(graph, root)?;
+
+    writeln!(writer, "# Generated by `button export ninja`.")?;
+    writeln!(writer)?;
+    writeln!(writer, "rule run")?;
+    writeln!(writer, "  command = $command")?;
+    writeln!(writer, "  description = $description")?;
+
+    for step in &steps {
+        let paths = |paths: &[String]| -> String {
+            paths
+                .iter()
+                .map(|p| format!(" {}", ninja_path(p)))
+                .collect()
+        };
+
+        writeln!(writer)?;
+        write!(
+            writer,
+            "build{}: run{}",
+            paths(&step.outputs),
+            paths(&step.inputs)
+        )?;
+        if !step.implicit_inputs.is_empty() {
+            write!(writer, " |{}", paths(&step.implicit_inputs))?;
+        }
+        writeln!(writer)?;
+        writeln!(writer, "  command = {}", ninja_value(&step.command))?;
+        writeln!(writer, "  description = {}", ninja_value(&step.description))?;
+        if step.generator {
+            // Keeps `ninja -t clean` from deleting the outputs, which include
+            // the build rules.
+            writeln!(writer, "  generator = 1")?;
+        }
+    }
+
+    for alias in aliases(graph, root) {
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "build {}: phony{}",
+            ninja_path(&alias.name),
+            alias
+                .targets
+                .iter()
+                .map(|p| format!(" {}", ninja_path(p)))
+                .collect::<String>()
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes the build graph as a GNU Makefile. `root` is the directory of the
+/// build rules. Tasks with multiple outputs use grouped targets, which require
+/// GNU Make 4.3 or later.
+pub fn to_make(
+    graph: &BuildGraph,
+    root: &Path,
+    writer: &mut dyn io::Write,
+) -> Result<(), Error> {
+    let steps = steps(graph, root)?;
+    let aliases = aliases(graph, root);
+
+    // Build everything that isn't used by another task.
+    let inputs: HashSet<&str> = steps
+        .iter()
+        .flat_map(|s| s.inputs.iter().chain(&s.implicit_inputs))
+        .map(String::as_str)
+        .collect();
+
+    let all: Vec<&str> = steps
+        .iter()
+        .flat_map(|s| &s.outputs)
+        .map(String::as_str)
+        .filter(|output| !inputs.contains(output))
+        .collect();
+
+    let phony: Vec<&str> = steps
+        .iter()
+        .filter(|s| s.phony)
+        .flat_map(|s| &s.outputs)
+        .map(String::as_str)
+        .chain(aliases.iter().map(|a| a.name.as_str()))
+        .filter(|name| *name != "all")
+        .collect();
+
+    let paths = |paths: &mut dyn Iterator<Item = &str>| -> String {
+        paths.map(|p| format!(" {}", make_path(p))).collect()
+    };
+
+    writeln!(writer, "# Generated by `button export make`.")?;
+    writeln!(writer)?;
+    writeln!(writer, ".PHONY: all{}", paths(&mut phony.iter().cloned()))?;
+
+    // An alias named "all" replaces the default target. It must come first
+    // so that it is the default goal.
+    match aliases.iter().find(|a| a.name == "all") {
+        Some(alias) => writeln!(
+            writer,
+            "all:{}",
+            paths(&mut alias.targets.iter().map(String::as_str))
+        )?,
+        None => writeln!(writer, "all:{}", paths(&mut all.iter().cloned()))?,
+    }
+
+    for step in &steps {
+        let separator = if step.outputs.len() > 1 { " &:" } else { ":" };
+
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "{}{}{}",
+            paths(&mut step.outputs.iter().map(String::as_str)).trim_start(),
+            separator,
+            paths(
+                &mut step
+                    .inputs
+                    .iter()
+                    .chain(&step.implicit_inputs)
+                    .map(String::as_str)
+            )
+        )?;
+        writeln!(writer, "\t{}", step.command.replace('$', "$$"))?;
+    }
+
+    for alias in aliases.iter().filter(|a| a.name != "all") {
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "{}:{}",
+            make_path(&alias.name),
+            paths(&mut alias.targets.iter().map(String::as_str))
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Escapes a path in a ninja file.
+fn ninja_path(path: &str) -> String {
+    path.replace('$', "$$")
+        .replace(' ', "$ ")
+        .replace(':', "$:")
+}
+
+/// Escapes a variable value in a ninja file.
+fn ninja_value(value: &str) -> String {
+    value.replace('$', "$$")
+}
+
+/// Escapes a path in a Makefile.
+fn make_path(path: &str) -> String {
+    path.replace('$', "$$")
+        .replace(' ', "\\ ")
+        .replace('#', "\\#")
+        .replace(':', "\\:")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::build_graph::FromRules;
+    use crate::rules::Rules;
+
+    fn graph() -> (BuildGraph, tempfile::TempDir) {
+        let data = r#"[
+        {
+            "inputs": [{"file": "foo.c"}],
+            "tasks": [
+                {"makedir": {"path": "obj"}},
+                {
+                    "command": {
+                        "program": "gcc",
+                        "args": ["-c", "foo.c", "-o", "obj/$foo bar.o"]
+                    }
+                }
+            ],
+            "outputs": [{"file": "obj/$foo bar.o"}]
+        }
+        ]"#;
+
+        // Only the source file exists. The compiler is left out.
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("foo.c"), "").unwrap();
+
+        let graph =
+            BuildGraph::from_rules(Rules::from_str(data).unwrap()).unwrap();
+
+        (graph, root)
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_to_ninja() {
+        let (graph, root) = graph();
+
+        let mut buf = Vec::new();
+        to_ninja(&graph, root.path(), &mut buf).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            r#"# Generated by `button export ninja`.
+
+rule run
+  command = $command
+  description = $description
+
+build obj obj/$$foo$ bar.o: run foo.c
+  command = mkdir -p obj && gcc -c foo.c -o "obj/\$$foo bar.o"
+  description = list of 2 tasks
+"#
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_to_make() {
+        let (graph, root) = graph();
+
+        let mut buf = Vec::new();
+        to_make(&graph, root.path(), &mut buf).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            r#"# Generated by `button export make`.
+
+.PHONY: all
+all: obj obj/$$foo\ bar.o
+
+obj obj/$$foo\ bar.o &: foo.c
+	mkdir -p obj && gcc -c foo.c -o "obj/\$$foo bar.o"
+"#
+        );
+    }
+}
@@ -406,6 +406,9 @@ impl<'a> Iterator for NeighborsIter<'a> {
 mod tests {
     use super::*;
 
+    use std::collections::HashSet;
+    use std::iter;
+
     #[test]
     fn test_smoke() {
         let mut g = Graph::new();
@@ -467,6 +470,30 @@ mod tests {
         assert_eq!(sccs[3], vec![7.into()]);
     }
 
+    #[test]
+    fn test_dfs() {
+        //  a → b → c
+        //      ↓
+        //  d → e
+        let mut graph = Graph::new();
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let c = graph.add_node("c");
+        let d = graph.add_node("d");
+        let e = graph.add_node("e");
+
+        graph.add_edge(a, b, ());
+        graph.add_edge(b, c, ());
+        graph.add_edge(b, e, ());
+        graph.add_edge(d, e, ());
+
+        let forward: HashSet<_> = graph.dfs(iter::once(b)).collect();
+        assert_eq!(forward, [b, c, e].iter().cloned().collect());
+
+        let reverse: HashSet<_> = graph.reverse_dfs(iter::once(e)).collect();
+        assert_eq!(reverse, [a, b, d, e].iter().cloned().collect());
+    }
+
     #[test]
     fn diff() {
         let mut g1 = Graph::new();
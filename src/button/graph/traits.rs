@@ -545,6 +545,40 @@ where
             + Sync,
         Error: Send,
         T: Clone + Send,
+    {
+        self.traverse_with_skip(
+            visit,
+            |_, _, _| {},
+            must_visit,
+            threads,
+            reverse,
+            init,
+        )
+    }
+
+    /// Like `traverse`, but also calls `skip` for each node that is reached
+    /// without being visited. That is, none of the node's parents were visited
+    /// or they all returned `false`.
+    fn traverse_with_skip<F, S, Error, T>(
+        &'a self,
+        visit: F,
+        skip: S,
+        must_visit: &IndexSet<NodeIndex>,
+        threads: usize,
+        reverse: bool,
+        init: T,
+    ) -> Result<(), Vec<(NodeIndex, Error)>>
+    where
+        Self: Sync + Visitable<bool> + Indexable<'a>,
+        Self::Node: Sync,
+        Self::Edge: Sync,
+        Self::Map: Send + Sync,
+        F: Fn(usize, NodeIndex, &Self::Node, &T) -> Result<bool, Error>
+            + Send
+            + Sync,
+        S: Fn(NodeIndex, &Self::Node, &T) + Send + Sync,
+        Error: Send,
+        T: Clone + Send,
     {
         let threads = cmp::max(threads, 1);
 
@@ -553,13 +587,15 @@ where
         crossbeam::scope(|scope| {
             let state = &state;
             let visit = &visit;
+            let skip = &skip;
 
             for tid in 0..threads {
                 let init = init.clone();
 
                 scope.spawn(move |_| {
                     traversal_worker(
-                        self, tid, state, visit, must_visit, reverse, init,
+                        self, tid, state, visit, skip, must_visit, reverse,
+                        init,
                     )
                 });
             }
@@ -583,6 +619,15 @@ where
         DepthFirstSearch::new(self, roots)
     }
 
+    /// Returns an iterator over the nodes in the graph, depth first, following
+    /// incoming edges instead of outgoing edges.
+    fn reverse_dfs<I>(&'a self, roots: I) -> DepthFirstSearch<'a, Self>
+    where
+        I: Iterator<Item = NodeIndex>,
+    {
+        DepthFirstSearch::reverse(self, roots)
+    }
+
     /// Finds nodes that are only present in this graph, not the other.
     ///
     /// Computes in `O(|V|)` time.
@@ -645,17 +690,20 @@ where
 }
 
 /// Graph traversal worker thread.
-fn traversal_worker<'a, G, F, T, Error>(
+#[allow(clippy::too_many_arguments)]
+fn traversal_worker<'a, G, F, S, T, Error>(
     g: &'a G,
     tid: usize,
     state: &TraversalState<G, Error>,
     visit: &F,
+    skip: &S,
     must_visit: &IndexSet<NodeIndex>,
     reverse: bool,
     init: T,
 ) where
     G: Neighbors<'a> + Indexable<'a> + Visitable<bool> + Algo<'a>,
     F: Fn(usize, NodeIndex, &G::Node, &T) -> Result<bool, Error> + Sync,
+    S: Fn(NodeIndex, &G::Node, &T) + Sync,
     Error: Send,
 {
     while let Some(index) = state.queue.pop() {
@@ -682,6 +730,7 @@ fn traversal_worker<'a, G, F, T, Error>(
         let keep_going = if do_visit {
             visit(tid, index, g.node_from_index(index), &init)
         } else {
+            skip(index, g.node_from_index(index), &init);
             Ok(false)
         };
 
@@ -876,6 +925,7 @@ pub struct DepthFirstSearch<'a, G> {
     graph: &'a G,
     stack: Vec<NodeIndex>,
     visited: IndexSet<NodeIndex>,
+    reverse: bool,
 }
 
 impl<'a, G: 'a> DepthFirstSearch<'a, G> {
@@ -887,6 +937,17 @@ impl<'a, G: 'a> DepthFirstSearch<'a, G> {
             graph,
             stack: roots.collect(),
             visited: IndexSet::new(),
+            reverse: false,
+        }
+    }
+
+    pub fn reverse<I>(graph: &'a G, roots: I) -> DepthFirstSearch<'a, G>
+    where
+        I: Iterator<Item = NodeIndex>,
+    {
+        DepthFirstSearch {
+            reverse: true,
+            ..DepthFirstSearch::new(graph, roots)
         }
     }
 }
@@ -900,7 +961,7 @@ where
     fn next(&mut self) -> Option<Self::Item> {
         let node = self.stack.pop()?;
 
-        for (succ, _) in self.graph.outgoing(node) {
+        for (succ, _) in self.graph.neighbors(node, self.reverse) {
             if self.visited.visit(succ) {
                 self.stack.push(succ);
             }
@@ -914,3 +975,13 @@ pub trait Graphviz {
     /// GraphViz formatting of the graph.
     fn graphviz(&self, f: &mut dyn io::Write) -> Result<(), io::Error>;
 }
+
+pub trait Mermaid {
+    /// Mermaid formatting of the graph. This can be embedded in Markdown.
+    fn mermaid(&self, f: &mut dyn io::Write) -> Result<(), io::Error>;
+}
+
+pub trait JsonGraph {
+    /// JSON formatting of the graph, including the types of nodes and edges.
+    fn json(&self, f: &mut dyn io::Write) -> Result<(), io::Error>;
+}
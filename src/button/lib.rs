@@ -23,14 +23,20 @@ extern crate nom;
 
 mod build;
 pub mod build_graph;
+pub mod check;
+pub mod compdb;
 mod detect;
+pub mod diff;
 pub mod error;
 pub mod events;
+pub mod export;
 pub mod graph;
+pub mod ninja;
 pub mod res;
 pub mod rules;
 pub mod server;
 pub mod state;
+pub mod stats;
 pub mod task;
 pub mod util;
 
@@ -0,0 +1,342 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Conversion of `build.ninja` files to build rules.
+//!
+//! This makes it possible to migrate a project that generates ninja files
+//! incrementally. Some ninja concepts don't have an equivalent and are
+//! approximated:
+//!
+//!  - Implicit and order-only dependencies become regular inputs.
+//!  - `phony` targets are replaced by their inputs wherever they are used.
+//!  - `deps = gcc` (or just a `depfile`) and `deps = msvc` select the Clang
+//!    and Cl input detection. The depfile itself is not read. Detection only
+//!    works for commands that don't need a shell. Otherwise, the depfile is
+//!    only an output of the command and header dependencies are not tracked.
+//!    These build statements are reported so that they can be fixed up.
+//!  - Commands that need a shell are run with `sh -c`. Response files are
+//!    written by the command before running it.
+//!  - Pools, `default` targets, `restat`, and `generator` are ignored.
+
+mod parser;
+
+pub use self::parser::{Manifest, ParseError};
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::detect::Detect;
+use crate::error::Error;
+use crate::res;
+use crate::rules::{Rule, Rules, Tags};
+use crate::task;
+use crate::util::{ArgBuf, Arguments};
+
+use self::parser::{shell_escape, Build};
+
+/// Characters that require a command to be run through the shell.
+const SHELL_CHARS: &str = "|&;<>()$`*?[]#~{}!\n";
+
+/// Build rules converted from a ninja file.
+pub struct Converted {
+    pub rules: Rules,
+
+    /// The first output of each build statement that has a depfile that can't
+    /// be used. Changes to the headers these include won't cause a rebuild.
+    pub untracked: Vec<String>,
+}
+
+/// Converts a parsed ninja file to build rules. Paths are left as they are,
+/// so the rules should be placed next to the ninja file.
+pub fn to_rules(manifest: &Manifest) -> Result<Converted, Error> {
+    // Map phony outputs to their inputs.
+    let mut phony: HashMap<&str, Vec<&str>> = HashMap::new();
+
+    for build in manifest.builds.iter().filter(|b| b.is_phony()) {
+        let inputs: Vec<&str> = all_inputs(build).collect();
+
+        for output in build.outputs.iter().chain(&build.implicit_outputs) {
+            phony.insert(output, inputs.clone());
+        }
+    }
+
+    let mut rules = Vec::new();
+    let mut untracked = Vec::new();
+
+    for build in manifest.builds.iter().filter(|b| !b.is_phony()) {
+        let mut inputs = res::Set::new();
+        for input in all_inputs(build) {
+            resolve_phony(input, &phony, &mut HashSet::new(), &mut |path| {
+                inputs.insert(res::Any::from(path));
+            });
+        }
+
+        let mut outputs: res::Set = build
+            .outputs
+            .iter()
+            .chain(&build.implicit_outputs)
+            .map(res::Any::from)
+            .collect();
+
+        let name = build
+            .outputs
+            .iter()
+            .chain(&build.implicit_outputs)
+            .next()
+            .map(String::as_str)
+            .unwrap_or_default();
+
+        let (command, depfile) =
+            to_command(manifest, build).ok_or_else(|| {
+                failure::format_err!(
+                    "build statement for '{}' has no command",
+                    name
+                )
+            })?;
+
+        // The command still writes the depfile, even though nothing reads it.
+        if let Some(depfile) = depfile {
+            outputs.insert(res::Any::from(depfile));
+            untracked.push(name.to_owned());
+        }
+
+        rules.push(Rule {
+            inputs,
+            outputs,
+            tasks: task::List::new(vec![command.into()]),
+            tags: Tags::new(),
+            generator: false,
+        });
+    }
+
+    Ok(Converted {
+        rules: Rules::new(rules),
+        untracked,
+    })
+}
+
+fn all_inputs(build: &Build) -> impl Iterator<Item = &str> {
+    build
+        .inputs
+        .iter()
+        .chain(&build.implicit_inputs)
+        .chain(&build.order_only_inputs)
+        .map(String::as_str)
+}
+
+/// Calls `f` with each path that isn't a phony target, replacing phony targets
+/// with their inputs.
+fn resolve_phony<'a>(
+    path: &'a str,
+    phony: &HashMap<&'a str, Vec<&'a str>>,
+    visited: &mut HashSet<&'a str>,
+    f: &mut dyn FnMut(&'a str),
+) {
+    match phony.get(path) {
+        Some(inputs) => {
+            if visited.insert(path) {
+                for input in inputs {
+                    resolve_phony(input, phony, visited, f);
+                }
+            }
+        }
+        None => f(path),
+    }
+}
+
+/// Creates the command for a build statement. Returns `None` if there is no
+/// command. If input detection isn't possible for the command, its depfile is
+/// returned as well.
+fn to_command(
+    manifest: &Manifest,
+    build: &Build,
+) -> Option<(task::Command, Option<String>)> {
+    let command = manifest.evaluate(build, "command");
+    if command.trim().is_empty() {
+        return None;
+    }
+
+    let depfile = manifest.evaluate(build, "depfile");
+    let detect = match manifest.evaluate(build, "deps").as_str() {
+        "gcc" => Some(Detect::Clang),
+        "msvc" => Some(Detect::Cl),
+        _ if !depfile.is_empty() => Some(Detect::Clang),
+        _ => None,
+    };
+
+    let rspfile = manifest.evaluate(build, "rspfile");
+
+    let simple = rspfile.is_empty()
+        && !command.contains(|c| SHELL_CHARS.contains(c) || c == '\\')
+        // Environment variable assignments (e.g., `FOO=bar cmd`).
+        && !command
+            .split_whitespace()
+            .take(1)
+            .any(|program| program.contains('='));
+
+    let mut task = if simple {
+        // Without any of `SHELL_CHARS` or backslashes, the only shell syntax
+        // left is splitting on whitespace and quoting. Parsing the command
+        // like a response file does the same. This is *not* shell word
+        // splitting in general.
+        let mut args = Arguments::from_response_file(&command);
+        let program = args.remove(0);
+        let program = PathBuf::from(&**program);
+        task::Command::new(program, args)
+    } else {
+        let mut script = String::new();
+
+        if !rspfile.is_empty() {
+            let content = manifest.evaluate(build, "rspfile_content");
+            script.push_str(&format!(
+                "printf '%s\\n' {} > {} && ",
+                shell_escape(&content),
+                shell_escape(&rspfile)
+            ));
+        }
+
+        script.push_str(&command);
+
+        let args: Arguments =
+            vec![ArgBuf::from("-c"), ArgBuf::from(script), ArgBuf::from("sh")]
+                .into();
+
+        task::Command::new("sh".into(), args)
+    };
+
+    let description = manifest.evaluate(build, "description");
+    if !description.is_empty() {
+        task.display(description);
+    }
+
+    // Input detection works by adding arguments to the command. These can't
+    // be forwarded reliably to the compiler through a shell script (e.g., in
+    // `gcc ... && strip ...`).
+    let depfile = match detect {
+        Some(detect) if simple => {
+            task = task.with_detect(detect);
+            None
+        }
+        _ => Some(depfile).filter(|d| !d.is_empty()),
+    };
+
+    Some((task, depfile))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::Path;
+
+    #[test]
+    fn test_to_rules() -> Result<(), Error> {
+        let manifest = Manifest::parse(
+            r#"
+rule cc
+  command = gcc -c $in -o $out
+  deps = gcc
+  depfile = $out.d
+  description = CC $out
+
+rule link
+  command = gcc $in -o $out && strip $out
+  deps = msvc
+
+rule gen
+  command = touch $out
+
+build gen.h: gen
+build headers: phony gen.h
+build foo.o: cc foo.c || headers
+build foo: link foo.o
+build all: phony foo
+"#,
+            Path::new("."),
+        )?;
+
+        let converted = to_rules(&manifest)?;
+        let rules: Vec<_> = converted.rules.iter().collect();
+
+        assert_eq!(rules.len(), 3);
+
+        let inputs = |rule: &Rule| -> Vec<String> {
+            rule.inputs.iter().map(|r| r.to_string()).collect()
+        };
+
+        // The phony target is replaced by its inputs. The program is added as
+        // an input.
+        assert_eq!(inputs(rules[1]), vec!["foo.c", "gcc", "gen.h"]);
+
+        let command = |rule: &Rule| match &rule.tasks[0] {
+            task::Any::Command(command) => command.clone(),
+            _ => unreachable!(),
+        };
+
+        let cc = command(rules[1]);
+        assert_eq!(cc.detect(), Detect::Clang);
+        assert_eq!(cc.process().program, Path::new("gcc"));
+        assert_eq!(cc.process().args.to_string(), "-c foo.c -o foo.o");
+        assert_eq!(cc.to_string(), "CC foo.o");
+
+        let link = command(rules[2]);
+        // Detection can't work through the shell.
+        assert_eq!(link.detect(), Detect::None);
+        assert_eq!(link.process().program, Path::new("sh"));
+        let script: &str = &link.process().args[1];
+        assert_eq!(script, "gcc foo.o -o foo && strip foo");
+
+        // The link command has no depfile, so nothing is lost.
+        assert!(converted.untracked.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compound_depfile() -> Result<(), Error> {
+        let manifest = Manifest::parse(
+            r#"
+rule cc
+  command = gcc -MD -MF $out.d -c $in -o $out && touch $out.stamp
+  depfile = $out.d
+
+build foo.o: cc foo.c
+"#,
+            Path::new("."),
+        )?;
+
+        let converted = to_rules(&manifest)?;
+        let rule = converted.rules.iter().next().unwrap();
+
+        let command = match &rule.tasks[0] {
+            task::Any::Command(command) => command,
+            _ => unreachable!(),
+        };
+
+        // Detection can't be used with a shell. The depfile is written by the
+        // command, but nothing reads it.
+        assert_eq!(command.detect(), Detect::None);
+        assert!(rule.outputs.contains(&res::Any::from("foo.o.d")));
+        assert!(!rule.inputs.contains(&res::Any::from("foo.o.d")));
+        assert_eq!(converted.untracked, vec!["foo.o"]);
+
+        Ok(())
+    }
+}
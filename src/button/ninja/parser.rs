@@ -0,0 +1,842 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Parsing of `build.ninja` files.
+//!
+//! See https://ninja-build.org/manual.html#ref_ninja_file for a description of
+//! the file format.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use derive_more::Display;
+
+use crate::error::{Error, ResultExt};
+
+/// Rule variables can refer to each other. This limits how deep that can go
+/// such that cycles don't overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+/// An error in the syntax of a ninja file.
+#[derive(Display, Debug)]
+#[display(fmt = "{}:{}: {}", "path.display()", line, message)]
+pub struct ParseError {
+    pub path: PathBuf,
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::error::Error for ParseError {}
+
+/// A string with unevaluated variable references.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EvalString(Vec<Token>);
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Literal(String),
+    Var(String),
+}
+
+impl EvalString {
+    fn push_char(&mut self, c: char) {
+        match self.0.last_mut() {
+            Some(Token::Literal(s)) => s.push(c),
+            _ => self.0.push(Token::Literal(c.to_string())),
+        }
+    }
+
+    fn push_var(&mut self, name: &str) {
+        self.0.push(Token::Var(name.to_owned()));
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Expands the variables in the string.
+    pub fn evaluate(&self, env: &dyn Env) -> String {
+        let mut s = String::new();
+
+        for token in &self.0 {
+            match token {
+                Token::Literal(literal) => s.push_str(literal),
+                Token::Var(name) => s.push_str(&env.lookup(name)),
+            }
+        }
+
+        s
+    }
+}
+
+/// Something that variables can be looked up in. Undefined variables expand
+/// to an empty string.
+pub trait Env {
+    fn lookup(&self, name: &str) -> String;
+}
+
+/// A `rule` declaration. The bindings are evaluated when they are needed by a
+/// build statement.
+#[derive(Debug, Default)]
+pub struct Rule {
+    pub name: String,
+    bindings: HashMap<String, EvalString>,
+}
+
+/// A `build` statement with its paths evaluated.
+#[derive(Debug)]
+pub struct Build {
+    /// The scope this statement was declared in.
+    scope: usize,
+
+    pub rule: Rc<Rule>,
+    pub outputs: Vec<String>,
+    pub implicit_outputs: Vec<String>,
+    pub inputs: Vec<String>,
+    pub implicit_inputs: Vec<String>,
+    pub order_only_inputs: Vec<String>,
+
+    /// The variables bound by the build statement itself. These are evaluated
+    /// when parsed.
+    bindings: HashMap<String, String>,
+}
+
+impl Build {
+    pub fn is_phony(&self) -> bool {
+        self.rule.name == "phony"
+    }
+}
+
+/// The variables and rules of a file. `subninja` creates a child scope.
+#[derive(Debug, Default)]
+struct Scope {
+    parent: Option<usize>,
+    vars: HashMap<String, String>,
+    rules: HashMap<String, Rc<Rule>>,
+}
+
+/// A parsed ninja file, including the files it includes.
+#[derive(Debug)]
+pub struct Manifest {
+    scopes: Vec<Scope>,
+    pub builds: Vec<Build>,
+}
+
+impl Manifest {
+    /// Parses a ninja file. `include` and `subninja` paths are relative to the
+    /// directory of this file.
+    pub fn from_path(path: &Path) -> Result<Manifest, Error> {
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+        let mut manifest = Manifest::new();
+        manifest.parse_file(dir, path, 0)?;
+        Ok(manifest)
+    }
+
+    /// Parses the contents of a ninja file. `include` and `subninja` paths are
+    /// relative to `dir`.
+    pub fn parse(input: &str, dir: &Path) -> Result<Manifest, Error> {
+        let mut manifest = Manifest::new();
+        manifest.parse_str(dir, Path::new("build.ninja"), input, 0)?;
+        Ok(manifest)
+    }
+
+    fn new() -> Manifest {
+        let phony = Rule {
+            name: "phony".into(),
+            bindings: HashMap::new(),
+        };
+
+        let mut root = Scope::default();
+        root.rules.insert(phony.name.clone(), Rc::new(phony));
+
+        Manifest {
+            scopes: vec![root],
+            builds: Vec::new(),
+        }
+    }
+
+    /// Evaluates a variable in the context of a build statement. This is how
+    /// `command`, `depfile`, etc. are found.
+    pub fn evaluate(&self, build: &Build, name: &str) -> String {
+        BuildEnv {
+            manifest: self,
+            build,
+            depth: 0,
+        }
+        .lookup(name)
+    }
+
+    fn lookup_var(&self, mut scope: usize, name: &str) -> Option<&str> {
+        loop {
+            let s = &self.scopes[scope];
+
+            if let Some(value) = s.vars.get(name) {
+                return Some(value);
+            }
+
+            scope = s.parent?;
+        }
+    }
+
+    fn lookup_rule(&self, mut scope: usize, name: &str) -> Option<Rc<Rule>> {
+        loop {
+            let s = &self.scopes[scope];
+
+            if let Some(rule) = s.rules.get(name) {
+                return Some(rule.clone());
+            }
+
+            scope = s.parent?;
+        }
+    }
+
+    fn parse_file(
+        &mut self,
+        dir: &Path,
+        path: &Path,
+        scope: usize,
+    ) -> Result<(), Error> {
+        let input = fs::read_to_string(path)
+            .with_context(|_| format!("Failed reading '{}'", path.display()))?;
+
+        self.parse_str(dir, path, &input, scope)
+    }
+
+    fn parse_str(
+        &mut self,
+        dir: &Path,
+        path: &Path,
+        input: &str,
+        scope: usize,
+    ) -> Result<(), Error> {
+        let error = |line: usize, message: String| ParseError {
+            path: path.to_path_buf(),
+            line,
+            message,
+        };
+
+        // The declaration that indented bindings belong to.
+        let mut pending = Pending::None;
+
+        for (line, indented, text) in logical_lines(input) {
+            if indented {
+                let (name, value) =
+                    parse_binding(&text).map_err(|e| error(line, e))?;
+
+                match &mut pending {
+                    Pending::None => {
+                        return Err(error(
+                            line,
+                            "unexpected indentation".into(),
+                        )
+                        .into());
+                    }
+                    Pending::Rule(rule) => {
+                        rule.bindings.insert(name, value);
+                    }
+                    Pending::Build(build) => {
+                        // Build bindings are evaluated in the file scope.
+                        let value = value.evaluate(&ScopeEnv {
+                            manifest: self,
+                            scope,
+                        });
+                        build.bindings.insert(name, value);
+                    }
+                    Pending::Pool => {}
+                }
+
+                continue;
+            }
+
+            self.finish(std::mem::replace(&mut pending, Pending::None), scope)
+                .map_err(|e| error(line, e))?;
+
+            let (keyword, rest) = split_keyword(&text);
+
+            match keyword {
+                "rule" => {
+                    let name = rest.trim();
+                    if !is_identifier(name) {
+                        return Err(error(
+                            line,
+                            format!("invalid rule name '{}'", name),
+                        )
+                        .into());
+                    }
+
+                    pending = Pending::Rule(Rule {
+                        name: name.to_owned(),
+                        bindings: HashMap::new(),
+                    });
+                }
+                "build" => {
+                    pending = Pending::Build(
+                        parse_build(rest).map_err(|e| error(line, e))?,
+                    );
+                }
+                "pool" => {
+                    pending = Pending::Pool;
+                }
+                "default" => {
+                    // Everything gets built.
+                }
+                "include" | "subninja" => {
+                    let (file, _) = parse_eval(rest.trim(), false)
+                        .map_err(|e| error(line, e))?;
+                    let file = file.evaluate(&ScopeEnv {
+                        manifest: self,
+                        scope,
+                    });
+                    let file = dir.join(file);
+
+                    if keyword == "include" {
+                        self.parse_file(dir, &file, scope)?;
+                    } else {
+                        self.scopes.push(Scope {
+                            parent: Some(scope),
+                            ..Scope::default()
+                        });
+                        let child = self.scopes.len() - 1;
+                        self.parse_file(dir, &file, child)?;
+                    }
+                }
+                _ => {
+                    // Top-level variables are evaluated immediately.
+                    let (name, value) =
+                        parse_binding(&text).map_err(|e| error(line, e))?;
+                    let value = value.evaluate(&ScopeEnv {
+                        manifest: self,
+                        scope,
+                    });
+                    self.scopes[scope].vars.insert(name, value);
+                }
+            }
+        }
+
+        let line = input.lines().count();
+        self.finish(pending, scope).map_err(|e| error(line, e))?;
+
+        Ok(())
+    }
+
+    /// Finishes a declaration once all of its bindings have been parsed.
+    fn finish(&mut self, pending: Pending, scope: usize) -> Result<(), String> {
+        match pending {
+            Pending::Rule(rule) => {
+                if !rule.bindings.contains_key("command") {
+                    return Err(format!("rule '{}' has no command", rule.name));
+                }
+
+                let rules = &mut self.scopes[scope].rules;
+                if rules.contains_key(&rule.name) {
+                    return Err(format!("duplicate rule '{}'", rule.name));
+                }
+
+                rules.insert(rule.name.clone(), Rc::new(rule));
+            }
+            Pending::Build(build) => {
+                let rule =
+                    self.lookup_rule(scope, &build.rule).ok_or_else(|| {
+                        format!("unknown build rule '{}'", build.rule)
+                    })?;
+
+                // Paths can refer to the build's own bindings.
+                let env = PathEnv {
+                    manifest: self,
+                    scope,
+                    bindings: &build.bindings,
+                };
+
+                let eval = |paths: &[EvalString]| -> Vec<String> {
+                    paths.iter().map(|p| p.evaluate(&env)).collect()
+                };
+
+                let build = Build {
+                    scope,
+                    rule,
+                    outputs: eval(&build.outputs),
+                    implicit_outputs: eval(&build.implicit_outputs),
+                    inputs: eval(&build.inputs),
+                    implicit_inputs: eval(&build.implicit_inputs),
+                    order_only_inputs: eval(&build.order_only_inputs),
+                    bindings: build.bindings,
+                };
+
+                self.builds.push(build);
+            }
+            Pending::Pool | Pending::None => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// A declaration that is still collecting its indented bindings.
+enum Pending {
+    None,
+    Rule(Rule),
+    Build(PendingBuild),
+    Pool,
+}
+
+#[derive(Default)]
+struct PendingBuild {
+    rule: String,
+    outputs: Vec<EvalString>,
+    implicit_outputs: Vec<EvalString>,
+    inputs: Vec<EvalString>,
+    implicit_inputs: Vec<EvalString>,
+    order_only_inputs: Vec<EvalString>,
+    bindings: HashMap<String, String>,
+}
+
+/// Looks up variables in a file scope.
+struct ScopeEnv<'a> {
+    manifest: &'a Manifest,
+    scope: usize,
+}
+
+impl<'a> Env for ScopeEnv<'a> {
+    fn lookup(&self, name: &str) -> String {
+        self.manifest
+            .lookup_var(self.scope, name)
+            .unwrap_or_default()
+            .to_owned()
+    }
+}
+
+/// Looks up variables for the paths of a build statement.
+struct PathEnv<'a> {
+    manifest: &'a Manifest,
+    scope: usize,
+    bindings: &'a HashMap<String, String>,
+}
+
+impl<'a> Env for PathEnv<'a> {
+    fn lookup(&self, name: &str) -> String {
+        match self.bindings.get(name) {
+            Some(value) => value.clone(),
+            None => self
+                .manifest
+                .lookup_var(self.scope, name)
+                .unwrap_or_default()
+                .to_owned(),
+        }
+    }
+}
+
+/// Looks up variables for the rule of a build statement. In order, this checks
+/// the special `$in` and `$out` variables, the build's bindings, the rule's
+/// bindings, and then the file scope.
+struct BuildEnv<'a> {
+    manifest: &'a Manifest,
+    build: &'a Build,
+    depth: usize,
+}
+
+impl<'a> Env for BuildEnv<'a> {
+    fn lookup(&self, name: &str) -> String {
+        let build = self.build;
+
+        match name {
+            "in" => return join(&build.inputs, " "),
+            "in_newline" => return join(&build.inputs, "\n"),
+            "out" => return join(&build.outputs, " "),
+            _ => {}
+        }
+
+        if let Some(value) = build.bindings.get(name) {
+            return value.clone();
+        }
+
+        if let Some(value) = build.rule.bindings.get(name) {
+            if self.depth >= MAX_DEPTH {
+                return String::new();
+            }
+
+            return value.evaluate(&BuildEnv {
+                depth: self.depth + 1,
+                ..*self
+            });
+        }
+
+        self.manifest
+            .lookup_var(build.scope, name)
+            .unwrap_or_default()
+            .to_owned()
+    }
+}
+
+fn join(paths: &[String], separator: &str) -> String {
+    paths
+        .iter()
+        .map(|p| shell_escape(p))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// Quotes a string such that it is safe to pass to `sh`.
+pub fn shell_escape(s: &str) -> Cow<'_, str> {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_+-./=,@%:".contains(c);
+
+    if !s.is_empty() && s.chars().all(safe) {
+        Cow::Borrowed(s)
+    } else {
+        Cow::Owned(format!("'{}'", s.replace('\'', r"'\''")))
+    }
+}
+
+/// Splits the input into logical lines, joining lines continued with `$` and
+/// skipping comments and blank lines. Returns the line number, whether the
+/// line is indented, and its text.
+fn logical_lines(input: &str) -> Vec<(usize, bool, String)> {
+    let mut lines = Vec::new();
+
+    let mut line_number = 1;
+    let mut start = 1;
+    let mut text = String::new();
+    let mut chars = input.chars().peekable();
+
+    let mut push = |start: usize, text: &mut String| {
+        let trimmed = text.trim_start();
+        if !trimmed.is_empty() && !trimmed.starts_with('#') {
+            let indented = text.starts_with(' ') || text.starts_with('\t');
+            lines.push((start, indented, text.trim_end().to_owned()));
+        }
+        text.clear();
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '$' => match chars.peek() {
+                Some('\n') | Some('\r') => {
+                    // Line continuation. Skip the line ending and the
+                    // indentation of the next line.
+                    if chars.next() == Some('\r') {
+                        chars.next();
+                    }
+                    line_number += 1;
+
+                    while let Some(' ') = chars.peek() {
+                        chars.next();
+                    }
+                }
+                Some(&next) => {
+                    // Keep escapes intact for the next stage.
+                    text.push(c);
+                    text.push(next);
+                    chars.next();
+                }
+                None => text.push(c),
+            },
+            '\n' => {
+                push(start, &mut text);
+                line_number += 1;
+                start = line_number;
+            }
+            '\r' => {}
+            c => text.push(c),
+        }
+    }
+
+    push(start, &mut text);
+
+    lines
+}
+
+fn is_identifier(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars().all(|c| {
+            c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.'
+        })
+}
+
+fn is_simple_var_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Splits off the first word of a line.
+fn split_keyword(text: &str) -> (&str, &str) {
+    match text.find([' ', '\t']) {
+        Some(i) => (&text[..i], &text[i..]),
+        None => (text, ""),
+    }
+}
+
+/// Parses a `name = value` binding.
+fn parse_binding(text: &str) -> Result<(String, EvalString), String> {
+    let text = text.trim_start();
+
+    let end = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || "_-.".contains(c)))
+        .unwrap_or(text.len());
+
+    let name = &text[..end];
+    if name.is_empty() {
+        return Err(format!("expected a variable name, got '{}'", text));
+    }
+
+    let rest = text[end..].trim_start();
+    if !rest.starts_with('=') {
+        return Err(format!("expected '=' after '{}'", name));
+    }
+
+    let (value, _) = parse_eval(rest[1..].trim_start(), false)?;
+
+    Ok((name.to_owned(), value))
+}
+
+/// Parses a string containing `$` escapes. If `path` is true, stops at the
+/// first unescaped space, colon, or pipe. Returns the rest of the input.
+fn parse_eval(text: &str, path: bool) -> Result<(EvalString, &str), String> {
+    let mut s = EvalString::default();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            ' ' | ':' | '|' if path => return Ok((s, &text[i..])),
+            '$' => match chars.next() {
+                Some((_, '$')) => s.push_char('$'),
+                Some((_, ' ')) => s.push_char(' '),
+                Some((_, ':')) => s.push_char(':'),
+                Some((start, '{')) => {
+                    let start = start + 1;
+                    let end = text[start..]
+                        .find('}')
+                        .map(|n| start + n)
+                        .ok_or_else(|| "unterminated '${'".to_owned())?;
+
+                    s.push_var(&text[start..end]);
+
+                    while let Some(&(i, _)) = chars.peek() {
+                        chars.next();
+                        if i == end {
+                            break;
+                        }
+                    }
+                }
+                Some((start, c)) if is_simple_var_char(c) => {
+                    let mut end = start + c.len_utf8();
+                    while let Some(&(i, c)) = chars.peek() {
+                        if !is_simple_var_char(c) {
+                            break;
+                        }
+                        end = i + c.len_utf8();
+                        chars.next();
+                    }
+
+                    s.push_var(&text[start..end]);
+                }
+                _ => return Err(format!("bad $-escape in '{}'", text)),
+            },
+            c => s.push_char(c),
+        }
+    }
+
+    Ok((s, ""))
+}
+
+/// Parses the rest of a `build` line:
+/// `outputs | implicit-outputs: rule inputs | implicit-inputs || order-only`.
+fn parse_build(text: &str) -> Result<PendingBuild, String> {
+    #[derive(Copy, Clone, PartialEq)]
+    enum Section {
+        Outputs,
+        ImplicitOutputs,
+        Rule,
+        Inputs,
+        ImplicitInputs,
+        OrderOnly,
+        Validations,
+    }
+
+    let mut build = PendingBuild::default();
+    let mut section = Section::Outputs;
+    let mut rest = text;
+
+    loop {
+        rest = rest.trim_start_matches([' ', '\t']);
+        if rest.is_empty() {
+            break;
+        }
+
+        let next = match section {
+            Section::Outputs | Section::ImplicitOutputs => {
+                if rest.starts_with(':') {
+                    rest = &rest[1..];
+                    section = Section::Rule;
+                    continue;
+                } else if rest.starts_with('|') && section == Section::Outputs {
+                    rest = &rest[1..];
+                    section = Section::ImplicitOutputs;
+                    continue;
+                }
+
+                None
+            }
+            Section::Rule => None,
+            _ => {
+                if rest.starts_with("||") {
+                    Some((2, Section::OrderOnly))
+                } else if rest.starts_with("|@") {
+                    Some((2, Section::Validations))
+                } else if rest.starts_with('|') {
+                    Some((1, Section::ImplicitInputs))
+                } else {
+                    None
+                }
+            }
+        };
+
+        if let Some((n, next)) = next {
+            rest = &rest[n..];
+            section = next;
+            continue;
+        }
+
+        let (path, remaining) = parse_eval(rest, true)?;
+        if path.is_empty() {
+            return Err(format!("unexpected '{}'", &rest[..1]));
+        }
+        rest = remaining;
+
+        match section {
+            Section::Outputs => build.outputs.push(path),
+            Section::ImplicitOutputs => build.implicit_outputs.push(path),
+            Section::Rule => {
+                build.rule = path.evaluate(&NoEnv);
+                section = Section::Inputs;
+            }
+            Section::Inputs => build.inputs.push(path),
+            Section::ImplicitInputs => build.implicit_inputs.push(path),
+            Section::OrderOnly => build.order_only_inputs.push(path),
+            Section::Validations => {}
+        }
+    }
+
+    if build.outputs.is_empty() && build.implicit_outputs.is_empty() {
+        return Err("expected output paths".into());
+    }
+
+    if build.rule.is_empty() {
+        return Err("expected a build rule".into());
+    }
+
+    Ok(build)
+}
+
+struct NoEnv;
+
+impl Env for NoEnv {
+    fn lookup(&self, _name: &str) -> String {
+        String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Manifest {
+        Manifest::parse(input, Path::new(".")).unwrap()
+    }
+
+    #[test]
+    fn test_logical_lines() {
+        assert_eq!(
+            logical_lines("# comment\nfoo = a $\n    b\n\n  x = $$\r\n"),
+            vec![
+                (2, false, "foo = a b".to_owned()),
+                (5, true, "  x = $$".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_eval() {
+        let (s, rest) = parse_eval("a$ b$:c$$${d}.o$e: rest", true).unwrap();
+        assert_eq!(rest, ": rest");
+        assert_eq!(
+            s,
+            EvalString(vec![
+                Token::Literal("a b:c$".into()),
+                Token::Var("d".into()),
+                Token::Literal(".o".into()),
+                Token::Var("e".into()),
+            ])
+        );
+
+        assert!(parse_eval("$!", false).is_err());
+        assert!(parse_eval("${foo", false).is_err());
+    }
+
+    #[test]
+    fn test_build() {
+        let manifest = parse(
+            "cflags = -O2
+rule cc
+  command = gcc $cflags $extra -c $in -o $out
+  description = CC $out
+  depfile = $out.d
+
+extra = -g
+build foo.o | foo.lst: cc foo.c | foo.h || gen
+  cflags = $cflags -Wall
+",
+        );
+
+        assert_eq!(manifest.builds.len(), 1);
+
+        let build = &manifest.builds[0];
+        assert_eq!(build.rule.name, "cc");
+        assert_eq!(build.outputs, vec!["foo.o"]);
+        assert_eq!(build.implicit_outputs, vec!["foo.lst"]);
+        assert_eq!(build.inputs, vec!["foo.c"]);
+        assert_eq!(build.implicit_inputs, vec!["foo.h"]);
+        assert_eq!(build.order_only_inputs, vec!["gen"]);
+
+        assert_eq!(
+            manifest.evaluate(build, "command"),
+            "gcc -O2 -Wall -g -c foo.c -o foo.o"
+        );
+        assert_eq!(manifest.evaluate(build, "description"), "CC foo.o");
+        assert_eq!(manifest.evaluate(build, "depfile"), "foo.o.d");
+    }
+
+    #[test]
+    fn test_shell_escape() {
+        assert_eq!(shell_escape("foo/bar.c"), "foo/bar.c");
+        assert_eq!(shell_escape("foo bar.c"), "'foo bar.c'");
+        assert_eq!(shell_escape("it's"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn test_errors() {
+        let dir = Path::new(".");
+        assert!(Manifest::parse("build foo: nope bar\n", dir).is_err());
+        assert!(Manifest::parse("rule cc\n  description = x\n", dir).is_err());
+        assert!(Manifest::parse("  foo = bar\n", dir).is_err());
+        assert!(Manifest::parse("build: phony\n", dir).is_err());
+    }
+}
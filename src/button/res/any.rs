@@ -28,6 +28,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
 
+use super::checksum::ChecksumAlgorithm;
 use super::dir::Dir;
 use super::file::File;
 use super::traits::{Resource, ResourceState};
@@ -46,6 +47,16 @@ pub enum Any {
     Dir(Dir),
 }
 
+impl Any {
+    /// The path to the resource, relative to the project root.
+    pub fn path(&self) -> &Path {
+        match self {
+            Any::File(ref x) => x.path(),
+            Any::Dir(ref x) => x.path(),
+        }
+    }
+}
+
 impl fmt::Display for Any {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -71,10 +82,14 @@ impl<P: AsRef<Path>> From<P> for Any {
 }
 
 impl Resource for Any {
-    fn state(&self, root: &Path) -> Result<ResourceState, Error> {
+    fn state(
+        &self,
+        root: &Path,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<ResourceState, Error> {
         match self {
-            Any::File(ref x) => x.state(root),
-            Any::Dir(ref x) => x.state(root),
+            Any::File(ref x) => x.state(root, algorithm),
+            Any::Dir(ref x) => x.state(root, algorithm),
         }
     }
 
@@ -0,0 +1,202 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use sha2::{self, Digest};
+
+use crate::util;
+
+/// The hash function used to compute resource checksums.
+///
+/// Checksums are only used for local change detection. They never leave the
+/// machine, so a fast hash function is preferable to a widely-supported one.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Eq,
+    PartialEq,
+    Hash,
+    Ord,
+    PartialOrd,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Blake3,
+}
+
+impl Default for ChecksumAlgorithm {
+    fn default() -> Self {
+        ChecksumAlgorithm::Sha256
+    }
+}
+
+impl ChecksumAlgorithm {
+    pub fn variants() -> [&'static str; 2] {
+        ["sha256", "blake3"]
+    }
+
+    /// Returns a new hasher for this algorithm.
+    pub fn hasher(self) -> Hasher {
+        match self {
+            ChecksumAlgorithm::Sha256 => {
+                Hasher::Sha256(sha2::Sha256::default())
+            }
+            ChecksumAlgorithm::Blake3 => {
+                Hasher::Blake3(Box::new(blake3::Hasher::new()))
+            }
+        }
+    }
+}
+
+impl FromStr for ChecksumAlgorithm {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(ChecksumAlgorithm::Sha256),
+            "blake3" => Ok(ChecksumAlgorithm::Blake3),
+            _ => Err("invalid checksum algorithm"),
+        }
+    }
+}
+
+impl fmt::Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChecksumAlgorithm::Sha256 => write!(f, "sha256"),
+            ChecksumAlgorithm::Blake3 => write!(f, "blake3"),
+        }
+    }
+}
+
+/// An incremental hasher for any of the supported checksum algorithms.
+pub enum Hasher {
+    Sha256(sha2::Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    /// Feeds more data into the hasher.
+    pub fn input(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(h) => h.input(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+        }
+    }
+
+    /// Consumes the hasher and returns the checksum.
+    pub fn result(self) -> Checksum {
+        match self {
+            Hasher::Sha256(h) => Checksum::Sha256(h.result().into()),
+            Hasher::Blake3(h) => Checksum::Blake3(h.finalize().into()),
+        }
+    }
+}
+
+/// The checksum of a resource. This records the algorithm that produced it
+/// such that switching algorithms is seen as a change to every resource.
+#[derive(
+    Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize,
+)]
+pub enum Checksum {
+    Sha256(util::Sha256),
+    Blake3(util::Blake3),
+}
+
+impl Checksum {
+    /// Computes the checksum of a stream.
+    pub fn from_reader<R>(
+        algorithm: ChecksumAlgorithm,
+        reader: R,
+    ) -> io::Result<Checksum>
+    where
+        R: io::Read,
+    {
+        Ok(match algorithm {
+            ChecksumAlgorithm::Sha256 => {
+                Checksum::Sha256(util::Sha256::from_reader(reader)?)
+            }
+            ChecksumAlgorithm::Blake3 => {
+                Checksum::Blake3(util::Blake3::from_reader(reader)?)
+            }
+        })
+    }
+
+    /// The checksum of no data at all.
+    pub fn empty(algorithm: ChecksumAlgorithm) -> Checksum {
+        algorithm.hasher().result()
+    }
+
+    /// Returns the algorithm that produced this checksum.
+    pub fn algorithm(&self) -> ChecksumAlgorithm {
+        match self {
+            Checksum::Sha256(_) => ChecksumAlgorithm::Sha256,
+            Checksum::Blake3(_) => ChecksumAlgorithm::Blake3,
+        }
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Checksum::Sha256(c) => write!(f, "sha256:{}", c),
+            Checksum::Blake3(c) => write!(f, "blake3:{}", c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty() {
+        let data: &[u8] = b"";
+
+        for &name in ChecksumAlgorithm::variants().iter() {
+            let algorithm: ChecksumAlgorithm = name.parse().unwrap();
+            assert_eq!(
+                Checksum::from_reader(algorithm, data).unwrap(),
+                Checksum::empty(algorithm)
+            );
+        }
+    }
+
+    #[test]
+    fn test_algorithms_differ() {
+        let data: &[u8] = b"hello world";
+
+        let a = Checksum::from_reader(ChecksumAlgorithm::Sha256, data).unwrap();
+        let b = Checksum::from_reader(ChecksumAlgorithm::Blake3, data).unwrap();
+
+        assert_eq!(a.algorithm(), ChecksumAlgorithm::Sha256);
+        assert_eq!(b.algorithm(), ChecksumAlgorithm::Blake3);
+        assert_ne!(a, b);
+    }
+}
@@ -26,10 +26,11 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use crate::error::Error;
-use crate::util::{PathExt, Sha256};
+use crate::util::PathExt;
 
 use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
 
+use super::checksum::{Checksum, ChecksumAlgorithm};
 use super::traits::{Resource, ResourceState};
 
 /// A directory resource. We don't care about the contents of this resource.
@@ -45,6 +46,11 @@ impl Dir {
         }
     }
 
+    /// The path to the directory, relative to the project root.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     fn delete_impl(&self, root: &Path) -> Result<(), io::Error> {
         let path = root.join(&self.path);
 
@@ -112,13 +118,17 @@ impl<'de> Deserialize<'de> for Dir {
 }
 
 impl Resource for Dir {
-    fn state(&self, root: &Path) -> Result<ResourceState, Error> {
+    fn state(
+        &self,
+        root: &Path,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<ResourceState, Error> {
         let path = root.join(&self.path);
         Ok(match path.metadata() {
             Ok(metadata) => {
                 if metadata.is_dir() {
                     // Use an empty hash to indicate existence.
-                    Ok(ResourceState::Checksum(Sha256::default()))
+                    Ok(ResourceState::Checksum(Checksum::empty(algorithm)))
                 } else {
                     Err(io::Error::new(io::ErrorKind::Other, "Not a directory"))
                 }
@@ -27,14 +27,13 @@ use std::io;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use sha2::{Digest, Sha256};
-
 use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
 
+use super::checksum::{Checksum, ChecksumAlgorithm};
 use super::traits::{Resource, ResourceState};
 
 use crate::error::{Error, ResultExt};
-use crate::util::{self, PathExt};
+use crate::util::PathExt;
 
 /// A file resource. This can actually be a file *or* directory.
 ///
@@ -52,8 +51,17 @@ impl File {
         }
     }
 
+    /// The path to the file, relative to the project root.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     /// Assumes this resource is a regular file and returns its checksum.
-    fn file_state(&self, root: &Path) -> Result<ResourceState, Error> {
+    fn file_state(
+        &self,
+        root: &Path,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<ResourceState, Error> {
         let path = root.join(&self.path);
         let f = match fs::File::open(&path) {
             Ok(f) => Ok(f),
@@ -68,15 +76,21 @@ impl File {
             format!("Could not open file '{}'", self.path.display())
         })?;
 
-        Ok(ResourceState::Checksum(util::Sha256::from_reader(f)?))
+        Ok(ResourceState::Checksum(Checksum::from_reader(
+            algorithm, f,
+        )?))
     }
 
     /// Assumes this resource is a directory and returns the checksum of its
     /// file contents.
-    fn dir_state(&self, root: &Path) -> Result<ResourceState, Error> {
+    fn dir_state(
+        &self,
+        root: &Path,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<ResourceState, Error> {
         let path = root.join(&self.path);
 
-        let mut hasher = Sha256::default();
+        let mut hasher = algorithm.hasher();
 
         let mut names = Vec::new();
 
@@ -96,7 +110,7 @@ impl File {
             }
         }
 
-        Ok(ResourceState::Checksum(hasher.result().into()))
+        Ok(ResourceState::Checksum(hasher.result()))
     }
 }
 
@@ -154,13 +168,17 @@ impl Resource for File {
     /// If a file, the checksum is of the contents of the file. If a directory,
     /// the checksum is of the sorted list of directory entries. Thus, if a file
     /// is added or removed from a directory, the checksum changes.
-    fn state(&self, root: &Path) -> Result<ResourceState, Error> {
+    fn state(
+        &self,
+        root: &Path,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<ResourceState, Error> {
         if self.path.is_dir() {
-            self.dir_state(root)
+            self.dir_state(root, algorithm)
         } else {
             // Assume its a file even if its not. It'll error out if there are
             // problems reading it.
-            self.file_state(root)
+            self.file_state(root, algorithm)
         }
     }
 
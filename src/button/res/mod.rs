@@ -19,11 +19,13 @@
 // THE SOFTWARE.
 
 mod any;
+mod checksum;
 mod dir;
 mod file;
 mod traits;
 
 pub use self::any::{Any, Set};
+pub use self::checksum::{Checksum, ChecksumAlgorithm, Hasher};
 pub use self::dir::Dir;
 pub use self::file::File;
-pub use self::traits::{Checksum, Resource, ResourceState};
+pub use self::traits::{Resource, ResourceState};
@@ -25,10 +25,9 @@ use std::path::Path;
 
 use serde::{Deserialize, Serialize};
 
-use crate::error::Error;
-use crate::util;
+use super::checksum::{Checksum, ChecksumAlgorithm};
 
-pub type Checksum = util::Sha256;
+use crate::error::Error;
 
 /// The state associated with a resource. This is stored in the build state and
 /// used to determine if a resource has changed.
@@ -64,8 +63,12 @@ pub trait Resource:
     Serialize + Ord + PartialOrd + Eq + PartialEq + Hash + fmt::Display
 {
     /// Gets the state of the resource. This is used to determine if it has
-    /// changed.
-    fn state(&self, root: &Path) -> Result<ResourceState, Error>;
+    /// changed. The given algorithm is used to compute the checksum.
+    fn state(
+        &self,
+        root: &Path,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<ResourceState, Error>;
 
     /// Deletes the resource. Care should be taken by the caller to not delete
     /// *input* resources. That is, resources that the build system did not
@@ -18,12 +18,15 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use std::fs;
 use std::io;
 use std::path::Path;
 use std::slice::{Iter, IterMut};
 
-use serde::{Deserialize, Serialize};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json as json;
 
 use crate::res;
@@ -33,7 +36,7 @@ use crate::error::{BuildError, ErrorKind, ResultExt};
 
 /// A rule in the build description. A build description is simply a list of
 /// rules.
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Debug, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct Rule {
     /// Inputs to the task.
@@ -46,10 +49,121 @@ pub struct Rule {
 
     /// The sequence of tasks to execute.
     pub tasks: task::List,
+
+    /// Arbitrary labels used to select which rules get built.
+    #[serde(default)]
+    pub tags: Tags,
+
+    /// If true, this rule generates the build rules. It is brought up to date
+    /// before the rules are loaded for the build. Its outputs are never
+    /// deleted.
+    #[serde(default)]
+    pub generator: bool,
+}
+
+impl Serialize for Rule {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Leave out the optional fields when they have their default value.
+        // Formats that are not self-describing always get every field.
+        let human_readable = serializer.is_human_readable();
+        let skip_tags = human_readable && self.tags.is_empty();
+        let skip_generator = human_readable && !self.generator;
+
+        let len = 5 - skip_tags as usize - skip_generator as usize;
+
+        let mut state = serializer.serialize_struct("Rule", len)?;
+        state.serialize_field("inputs", &self.inputs)?;
+        state.serialize_field("outputs", &self.outputs)?;
+        state.serialize_field("tasks", &self.tasks)?;
+
+        if skip_tags {
+            state.skip_field("tags")?;
+        } else {
+            state.serialize_field("tags", &self.tags)?;
+        }
+
+        if skip_generator {
+            state.skip_field("generator")?;
+        } else {
+            state.serialize_field("generator", &self.generator)?;
+        }
+
+        state.end()
+    }
+}
+
+/// Labels attached to a rule. These carry over to the rule's task node in the
+/// build graph.
+pub type Tags = BTreeSet<String>;
+
+/// Something that can be requested to be built. This is either a resource or
+/// another alias.
+#[derive(
+    Serialize, Deserialize, Clone, Ord, Eq, PartialOrd, PartialEq, Hash, Debug,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Target {
+    File(res::File),
+    Dir(res::Dir),
+    Alias(String),
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::File(x) => x.fmt(f),
+            Target::Dir(x) => x.fmt(f),
+            Target::Alias(x) => x.fmt(f),
+        }
+    }
+}
+
+/// Named groups of targets. Building an alias builds everything in its group.
+/// An alias does not correspond to anything on disk.
+pub type Aliases = BTreeMap<String, BTreeSet<Target>>;
+
+/// The object form of the rules file. This allows extra information to be
+/// specified alongside the list of rules.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    rules: Vec<Rule>,
+
+    #[serde(default)]
+    aliases: Aliases,
+
+    #[serde(default, rename = "default-tags")]
+    default_tags: Tags,
+}
+
+/// Either form of the rules file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AnyRulesFile {
+    List(Vec<Rule>),
+    Object(RulesFile),
+}
+
+#[derive(Serialize)]
+struct RulesFileRef<'a> {
+    rules: &'a [Rule],
+    aliases: &'a Aliases,
+    #[serde(rename = "default-tags")]
+    default_tags: &'a Tags,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
-pub struct Rules(Vec<Rule>);
+/// The build description. This is either a list of rules or, if more than
+/// just the rules need to be specified, an object of the form
+/// `{"rules": [...], "aliases": {...}, "default-tags": [...]}`.
+#[derive(Debug, PartialEq)]
+pub struct Rules {
+    rules: Vec<Rule>,
+    aliases: Aliases,
+    default_tags: Tags,
+}
 
 impl Rules {
     pub fn new(mut rules: Vec<Rule>) -> Rules {
@@ -59,7 +173,35 @@ impl Rules {
             r.tasks.known_outputs(&mut r.outputs);
         }
 
-        Rules(rules)
+        Rules {
+            rules,
+            aliases: Aliases::new(),
+            default_tags: Tags::new(),
+        }
+    }
+
+    /// Sets the named groups of targets.
+    pub fn with_aliases(mut self, aliases: Aliases) -> Rules {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Returns the named groups of targets.
+    pub fn aliases(&self) -> &Aliases {
+        &self.aliases
+    }
+
+    /// Sets the tags that are built when no tags or targets are requested.
+    /// Untagged rules are always built by default.
+    pub fn with_default_tags(mut self, default_tags: Tags) -> Rules {
+        self.default_tags = default_tags;
+        self
+    }
+
+    /// Returns the tags that are built by default. If this is empty,
+    /// everything is built by default.
+    pub fn default_tags(&self) -> &Tags {
+        &self.default_tags
     }
 
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Rules, BuildError> {
@@ -80,24 +222,88 @@ impl Rules {
         Ok(rules)
     }
 
-    fn from_reader<R>(reader: R) -> Result<Rules, json::error::Error>
+    fn from_reader<R>(mut reader: R) -> Result<Rules, json::error::Error>
     where
         R: io::Read,
     {
-        Ok(Self::new(json::from_reader(reader)?))
+        let mut s = String::new();
+        reader
+            .read_to_string(&mut s)
+            .map_err(json::error::Error::io)?;
+        Self::parse(&s)
     }
 
     #[cfg(test)]
     pub fn from_str(s: &str) -> Result<Rules, json::error::Error> {
-        Ok(Self::new(json::from_str(s)?))
+        Self::parse(s)
+    }
+
+    fn parse(s: &str) -> Result<Rules, json::error::Error> {
+        // Parse the string directly, rather than going through a
+        // `json::Value`, so that errors keep their line numbers.
+        if s.trim_start().starts_with('{') {
+            Ok(json::from_str::<RulesFile>(s)?.into())
+        } else {
+            Ok(Self::new(json::from_str(s)?))
+        }
     }
 
     pub fn iter(&self) -> Iter<'_, Rule> {
-        self.0.iter()
+        self.rules.iter()
     }
 
     pub fn iter_mut(&mut self) -> IterMut<'_, Rule> {
-        self.0.iter_mut()
+        self.rules.iter_mut()
+    }
+}
+
+impl Serialize for Rules {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Only use the object form when it's needed. Formats that are not
+        // self-describing always get the object form so that they can be
+        // deserialized.
+        if serializer.is_human_readable()
+            && self.aliases.is_empty()
+            && self.default_tags.is_empty()
+        {
+            self.rules.serialize(serializer)
+        } else {
+            RulesFileRef {
+                rules: &self.rules,
+                aliases: &self.aliases,
+                default_tags: &self.default_tags,
+            }
+            .serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Rules {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let file = if deserializer.is_human_readable() {
+            match AnyRulesFile::deserialize(deserializer)? {
+                AnyRulesFile::List(rules) => return Ok(Rules::new(rules)),
+                AnyRulesFile::Object(file) => file,
+            }
+        } else {
+            RulesFile::deserialize(deserializer)?
+        };
+
+        Ok(file.into())
+    }
+}
+
+impl From<RulesFile> for Rules {
+    fn from(file: RulesFile) -> Rules {
+        Rules::new(file.rules)
+            .with_aliases(file.aliases)
+            .with_default_tags(file.default_tags)
     }
 }
 
@@ -106,7 +312,7 @@ impl IntoIterator for Rules {
     type IntoIter = ::std::vec::IntoIter<Rule>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        self.rules.into_iter()
     }
 }
 
@@ -150,7 +356,86 @@ mod tests {
                 inputs: inputs.into_iter().collect(),
                 outputs: outputs.into_iter().collect(),
                 tasks: tasks.into(),
+                tags: Tags::new(),
+                generator: false,
             }])
         );
     }
+
+    #[test]
+    fn test_aliases() {
+        let data = r#"{
+            "rules": [{
+                "inputs": [{"file": "foo.c"}],
+                "outputs": [{"file": "foo"}],
+                "tasks": [{"command": {"program": "gcc", "args": ["foo.c"]}}]
+            }],
+            "aliases": {
+                "all": [{"alias": "test"}, {"file": "foo"}],
+                "test": []
+            }
+        }"#;
+
+        let rules = Rules::from_str(data).unwrap();
+
+        assert_eq!(rules.iter().count(), 1);
+        assert_eq!(
+            rules.aliases()["all"],
+            vec![
+                Target::Alias("test".into()),
+                Target::File(File::from("foo")),
+            ]
+            .into_iter()
+            .collect()
+        );
+        assert!(rules.aliases()["test"].is_empty());
+
+        // Round trip through both a self-describing format and one that
+        // isn't.
+        let json = json::to_string(&rules).unwrap();
+        assert_eq!(Rules::from_str(&json).unwrap(), rules);
+
+        let bytes = bincode::serialize(&rules).unwrap();
+        assert_eq!(bincode::deserialize::<Rules>(&bytes).unwrap(), rules);
+    }
+
+    #[test]
+    fn test_list_form() {
+        let rules = Rules::from_str("[]").unwrap();
+        assert_eq!(json::to_string(&rules).unwrap(), "[]");
+    }
+
+    #[test]
+    fn test_tags() {
+        let data = r#"{
+            "rules": [
+                {
+                    "tasks": [{"command": {"program": "test.sh", "args": []}}],
+                    "tags": ["test", "slow"]
+                },
+                {
+                    "tasks": [{"command": {"program": "build.sh", "args": []}}]
+                }
+            ],
+            "default-tags": ["build"]
+        }"#;
+
+        let rules = Rules::from_str(data).unwrap();
+
+        let tags: Vec<_> = rules.iter().map(|r| r.tags.len()).collect();
+        assert_eq!(tags, vec![2, 0]);
+        assert!(rules.iter().next().unwrap().tags.contains("slow"));
+        assert_eq!(
+            rules.default_tags(),
+            &vec!["build".to_string()].into_iter().collect()
+        );
+
+        // Untagged rules don't gain an empty list of tags.
+        let json = json::to_string(&rules).unwrap();
+        assert_eq!(json.matches("\"tags\"").count(), 1);
+        assert_eq!(Rules::from_str(&json).unwrap(), rules);
+
+        let bytes = bincode::serialize(&rules).unwrap();
+        assert_eq!(bincode::deserialize::<Rules>(&bytes).unwrap(), rules);
+    }
 }
@@ -0,0 +1,212 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+//! An append-only journal of changes made to the build state during a build.
+//!
+//! The build state is only written out once the build has finished. If the
+//! build is interrupted (e.g., the process is killed), all of the work done so
+//! far would be forgotten and redone on the next build. To avoid that, changes
+//! to the state are appended to a journal as they happen. When the state is
+//! loaded again, the journal is replayed on top of it. The journal is deleted
+//! whenever the full state is written to disk.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use bincode;
+use serde::{Deserialize, Serialize};
+
+use crate::build_graph::TaskNode;
+use crate::detect::Detected;
+use crate::error::{Error, Fail, ResultExt};
+use crate::res::{self, ResourceState};
+
+/// Identifies a file as a journal. This is followed by the journal version.
+const MAGIC: &str = "button-journal";
+
+/// Version of the journal format. Journals with a different version are
+/// ignored. Entries hold the same nodes and resource states as the build state,
+/// so this is the schema version of the build state.
+const VERSION: u32 = super::migrate::CURRENT;
+
+/// A single change to the build state. Nodes are identified by their value
+/// rather than their index since indices are not stable across builds.
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Entry {
+    /// The state of a resource was computed.
+    Checksum(res::Any, ResourceState),
+
+    /// A task finished successfully with the given detected inputs and
+    /// outputs for each of its subtasks.
+    Task(TaskNode, Vec<Detected>),
+}
+
+/// Returns the path to the journal associated with the given state path.
+pub fn path(state: &Path) -> PathBuf {
+    let mut path = state.as_os_str().to_owned();
+    path.push(".journal");
+    PathBuf::from(path)
+}
+
+/// Deletes the journal associated with the given state path. There is no error
+/// if it does not exist.
+pub fn remove(state: &Path) -> io::Result<()> {
+    match fs::remove_file(path(state)) {
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        x => x,
+    }
+}
+
+/// Reads all of the entries in the journal associated with the given state
+/// path. If the build was interrupted while writing an entry, the journal may
+/// be truncated. Reading stops at the first entry that cannot be read.
+pub fn read(state: &Path) -> Result<Vec<Entry>, Error> {
+    let path = path(state);
+
+    let f = match fs::File::open(&path) {
+        Ok(f) => f,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+            return Ok(Vec::new());
+        }
+        Err(err) => {
+            return Err(err
+                .context(format!("Failed opening '{}'", path.display()))
+                .into());
+        }
+    };
+
+    let mut reader = io::BufReader::new(f);
+
+    let magic: Result<String, _> = bincode::deserialize_from(&mut reader);
+    let version: Result<u32, _> = bincode::deserialize_from(&mut reader);
+
+    match (magic, version) {
+        (Ok(ref magic), Ok(VERSION)) if magic == MAGIC => {}
+        _ => {
+            // Not a journal that we can read. This can only lose work, so it
+            // is fine to ignore it.
+            return Ok(Vec::new());
+        }
+    }
+
+    let mut entries = Vec::new();
+
+    while let Ok(entry) = bincode::deserialize_from(&mut reader) {
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+/// A journal opened for appending.
+pub struct Journal {
+    writer: io::BufWriter<fs::File>,
+}
+
+impl Journal {
+    /// Opens the journal associated with the given state path for appending.
+    /// It is created if it does not already exist.
+    pub fn open(state: &Path) -> Result<Journal, Error> {
+        let path = path(state);
+
+        let f = fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&path)
+            .with_context(|_| format!("Failed opening '{}'", path.display()))?;
+
+        let is_empty = f.metadata()?.len() == 0;
+
+        let mut journal = Journal {
+            writer: io::BufWriter::new(f),
+        };
+
+        if is_empty {
+            bincode::serialize_into(&mut journal.writer, MAGIC)?;
+            bincode::serialize_into(&mut journal.writer, &VERSION)?;
+            journal.writer.flush()?;
+        }
+
+        Ok(journal)
+    }
+
+    /// Appends an entry to the journal. The entry is flushed to disk
+    /// immediately so that it survives the process getting killed.
+    pub fn append(&mut self, entry: &Entry) -> Result<(), Error> {
+        bincode::serialize_into(&mut self.writer, entry)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::tempdir;
+
+    use crate::res::Checksum;
+
+    #[test]
+    fn test_truncated() {
+        let dir = tempdir().unwrap();
+        let state = dir.path().join("state");
+
+        {
+            let mut journal = Journal::open(&state).unwrap();
+            journal
+                .append(&Entry::Checksum(
+                    res::File::new("foo.c").into(),
+                    ResourceState::Missing,
+                ))
+                .unwrap();
+        }
+
+        {
+            // Reopening shouldn't write another header.
+            let mut journal = Journal::open(&state).unwrap();
+            journal
+                .append(&Entry::Checksum(
+                    res::File::new("bar.c").into(),
+                    ResourceState::Checksum(Checksum::empty(
+                        res::ChecksumAlgorithm::Blake3,
+                    )),
+                ))
+                .unwrap();
+        }
+
+        assert_eq!(read(&state).unwrap().len(), 2);
+
+        // Simulate getting killed in the middle of writing an entry.
+        let len = fs::metadata(path(&state)).unwrap().len();
+        fs::OpenOptions::new()
+            .write(true)
+            .open(path(&state))
+            .unwrap()
+            .set_len(len - 4)
+            .unwrap();
+
+        assert_eq!(read(&state).unwrap().len(), 1);
+
+        remove(&state).unwrap();
+        assert_eq!(read(&state).unwrap().len(), 0);
+        remove(&state).unwrap();
+    }
+}
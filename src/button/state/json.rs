@@ -0,0 +1,235 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+//! A human-readable representation of the build state.
+//!
+//! The binary build state refers to nodes by their internal index, which isn't
+//! meaningful outside of a single process. Instead, nodes are assigned an ID
+//! based on their position in the list of nodes. Edges, the queue, and
+//! checksums refer to nodes by this ID.
+
+use std::collections::HashMap;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+use serde_json as json;
+
+use super::{BuildState, StateError};
+
+use crate::build_graph::{BuildGraph, Edge, Node};
+use crate::error::Error;
+use crate::graph::{Edges, Indexable, NodeIndex, Nodes};
+use crate::res::ResourceState;
+
+#[derive(Serialize, Deserialize)]
+struct JsonNode {
+    id: usize,
+    node: Node,
+
+    /// The stored state of the resource. `None` if the build system does not
+    /// own this resource.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    checksum: Option<ResourceState>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonEdge {
+    from: usize,
+    to: usize,
+    edge: Edge,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonState {
+    nodes: Vec<JsonNode>,
+    edges: Vec<JsonEdge>,
+    queue: Vec<usize>,
+}
+
+impl BuildState {
+    /// Writes the state as pretty-printed JSON.
+    pub fn to_json<W: io::Write>(&self, writer: W) -> Result<(), Error> {
+        let mut ids = HashMap::new();
+        let mut nodes = Vec::new();
+
+        for (id, index) in self.graph.nodes().enumerate() {
+            ids.insert(index, id);
+            nodes.push(JsonNode {
+                id,
+                node: self.graph.node_from_index(index).clone(),
+                checksum: self.checksums.get(&index).cloned(),
+            });
+        }
+
+        let edges = self
+            .graph
+            .edges()
+            .map(|index| {
+                let ((a, b), edge) = self.graph.edge_from_index(index);
+                JsonEdge {
+                    from: ids[&a],
+                    to: ids[&b],
+                    edge: *edge,
+                }
+            })
+            .collect();
+
+        let queue = self.queue.iter().map(|index| ids[index]).collect();
+
+        let state = JsonState {
+            nodes,
+            edges,
+            queue,
+        };
+
+        json::to_writer_pretty(writer, &state)?;
+
+        Ok(())
+    }
+
+    /// Reads the state from JSON as written by `to_json`. The result is
+    /// validated to make sure it is a well-formed build graph.
+    pub fn from_json<R: io::Read>(reader: R) -> Result<BuildState, Error> {
+        let state: JsonState = json::from_reader(reader)?;
+
+        let mut graph = BuildGraph::new();
+        let mut checksums = HashMap::new();
+        let mut indices: HashMap<usize, NodeIndex> = HashMap::new();
+
+        for JsonNode { id, node, checksum } in state.nodes {
+            if indices.contains_key(&id) {
+                return Err(StateError::DuplicateId(id).into());
+            }
+
+            if graph.node_to_index(&node).is_some() {
+                return Err(StateError::DuplicateNode(id).into());
+            }
+
+            if let (Node::Task(_), Some(_)) = (&node, &checksum) {
+                return Err(StateError::TaskChecksum(id).into());
+            }
+
+            let index = graph.add_node(node);
+            indices.insert(id, index);
+
+            if let Some(checksum) = checksum {
+                checksums.insert(index, checksum);
+            }
+        }
+
+        let index_of = |id: usize| -> Result<NodeIndex, Error> {
+            indices
+                .get(&id)
+                .cloned()
+                .ok_or_else(|| StateError::UnknownId(id).into())
+        };
+
+        for JsonEdge { from, to, edge } in state.edges {
+            let (a, b) = (index_of(from)?, index_of(to)?);
+
+            // The graph is bipartite. Resources only connect to tasks and
+            // tasks only connect to resources.
+            match (graph.node_from_index(a), graph.node_from_index(b)) {
+                (Node::Resource(_), Node::Task(_))
+                | (Node::Task(_), Node::Resource(_)) => {}
+                _ => return Err(StateError::InvalidEdge(from, to).into()),
+            }
+
+            graph.add_edge(a, b, edge);
+        }
+
+        let queue = state
+            .queue
+            .into_iter()
+            .map(index_of)
+            .collect::<Result<_, _>>()?;
+
+        Ok(BuildState {
+            graph,
+            queue,
+            checksums,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::build_graph::FromRules;
+    use crate::graph::GraphBase;
+    use crate::rules::Rules;
+
+    #[test]
+    fn test_round_trip() {
+        let rules = Rules::from_str(
+            r#"[{
+                "inputs": [{"file": "foo.c"}],
+                "tasks": [{"command": {"program": "gcc", "args": ["foo.c"]}}],
+                "outputs": [{"file": "foo"}]
+            }]"#,
+        )
+        .unwrap();
+
+        let mut state =
+            BuildState::from_graph(BuildGraph::from_rules(rules).unwrap());
+
+        let input = state.queue[0];
+        state.checksums.insert(input, ResourceState::Missing);
+
+        let mut data = Vec::new();
+        state.to_json(&mut data).unwrap();
+
+        let loaded = BuildState::from_json(&data[..]).unwrap();
+        assert_eq!(loaded.graph.node_count(), state.graph.node_count());
+        assert_eq!(loaded.graph.edge_count(), state.graph.edge_count());
+        assert_eq!(loaded.queue.len(), state.queue.len());
+
+        for (a, b) in loaded.queue.iter().zip(state.queue.iter()) {
+            assert_eq!(
+                loaded.graph.node_from_index(*a),
+                state.graph.node_from_index(*b)
+            );
+        }
+
+        assert_eq!(loaded.checksums.len(), 1);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let data = r#"{
+            "nodes": [{"id": 0, "node": {"Resource": {"file": "foo.c"}}}],
+            "edges": [],
+            "queue": [1]
+        }"#;
+
+        assert!(BuildState::from_json(data.as_bytes()).is_err());
+
+        let data = r#"{
+            "nodes": [
+                {"id": 0, "node": {"Resource": {"file": "foo.c"}}},
+                {"id": 1, "node": {"Resource": {"file": "bar.c"}}}
+            ],
+            "edges": [{"from": 0, "to": 1, "edge": "Explicit"}],
+            "queue": []
+        }"#;
+
+        assert!(BuildState::from_json(data.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,283 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+//! Migrations between versions of the build state schema.
+//!
+//! Each time the serialized representation of `BuildState` changes, the schema
+//! version is bumped and a frozen copy of the previous representation is added
+//! here along with a function that upgrades it to the next version. Loading an
+//! old state then runs each upgrade in sequence until it is current.
+//!
+//! The version only changes once per release. Changes to the representation
+//! between releases are part of the same version, since no state written by a
+//! release has that representation.
+
+use std::collections::HashMap;
+use std::io;
+
+use bincode;
+use serde::Deserialize;
+
+use super::{BuildState, StateError};
+
+use crate::build_graph::{BuildGraph, Edge, Node};
+use crate::error::{Error, ResultExt};
+use crate::graph::{Edges, Graph, Indexable, NodeIndex, Nodes};
+use crate::res::{self, ResourceState};
+use crate::task;
+use crate::util::Sha256;
+
+/// The most recent schema version. This is the version that is always written.
+pub const CURRENT: u32 = 2;
+
+/// Maps the version strings used before the state had a schema version to
+/// their equivalent schema version.
+pub fn legacy_version(version: &str) -> Option<u32> {
+    match version {
+        // Checksums were always SHA-256.
+        "0.1.0" => Some(1),
+        _ => None,
+    }
+}
+
+/// Reads a state of an older schema version and upgrades it to the current
+/// version. Returns `None` if there is no migration path from that version.
+pub fn migrate<R: io::Read>(
+    version: u32,
+    reader: R,
+) -> Option<Result<BuildState, Error>> {
+    let result = match version {
+        1 => bincode::deserialize_from(reader).map(V1::upgrade),
+        _ => return None,
+    };
+
+    Some(
+        result
+            .map_err(StateError::corrupt)
+            .with_context(|_| {
+                format!(
+                    "Failed migrating build state from version {} to {}",
+                    version, CURRENT
+                )
+            })
+            .map_err(Error::from),
+    )
+}
+
+/// Version 1: Resource checksums are a bare SHA-256 and task nodes are only
+/// the list of tasks.
+#[derive(Deserialize)]
+struct V1 {
+    graph: Graph<V1Node, Edge>,
+    queue: Vec<NodeIndex>,
+    checksums: HashMap<NodeIndex, V1ResourceState>,
+}
+
+#[derive(Deserialize, Eq, PartialEq, Hash)]
+#[cfg_attr(test, derive(serde::Serialize))]
+enum V1Node {
+    Resource(res::Any),
+    Task(task::List),
+}
+
+#[derive(Deserialize)]
+enum V1ResourceState {
+    Missing,
+    Checksum(Sha256),
+}
+
+impl V1 {
+    fn upgrade(self) -> BuildState {
+        // The graph is rebuilt rather than converted in place. Thus, indices
+        // may change if nodes were removed from the old graph and need to be
+        // mapped to the new ones.
+        let mut graph = BuildGraph::new();
+        let mut indices = HashMap::new();
+
+        for index in self.graph.nodes() {
+            let node = match self.graph.node_from_index(index) {
+                V1Node::Resource(r) => Node::Resource(r.clone()),
+                V1Node::Task(t) => Node::Task(t.clone().into()),
+            };
+
+            indices.insert(index, graph.add_node(node));
+        }
+
+        for index in self.graph.edges() {
+            let ((a, b), weight) = self.graph.edge_from_index(index);
+            graph.add_edge(indices[&a], indices[&b], *weight);
+        }
+
+        let queue = self
+            .queue
+            .iter()
+            .filter_map(|index| indices.get(index).cloned())
+            .collect();
+
+        let checksums = self
+            .checksums
+            .into_iter()
+            .filter_map(|(index, state)| {
+                let state = match state {
+                    V1ResourceState::Missing => ResourceState::Missing,
+                    V1ResourceState::Checksum(c) => {
+                        ResourceState::Checksum(res::Checksum::Sha256(c))
+                    }
+                };
+
+                indices.get(&index).map(|&index| (index, state))
+            })
+            .collect();
+
+        BuildState {
+            graph,
+            queue,
+            checksums,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::Serialize;
+
+    use crate::build_graph::{FromRules, TaskNode};
+    use crate::graph::GraphBase;
+    use crate::rules::Rules;
+    use crate::state::StateError;
+
+    #[derive(Serialize)]
+    enum OldResourceState {
+        Missing,
+        Checksum(Sha256),
+    }
+
+    fn graph() -> BuildGraph {
+        let rules = Rules::from_str(
+            r#"[{
+                "inputs": [{"file": "foo.c"}],
+                "tasks": [{"command": {"program": "gcc", "args": ["foo.c"]}}],
+                "outputs": [{"file": "foo"}]
+            }]"#,
+        )
+        .unwrap();
+
+        BuildGraph::from_rules(rules).unwrap()
+    }
+
+    /// Converts the graph to how it was stored in version 1. The node indices
+    /// stay the same.
+    fn old_graph(graph: &BuildGraph) -> Graph<V1Node, Edge> {
+        let mut old = Graph::new();
+
+        for index in graph.nodes() {
+            old.add_node(match graph.node_from_index(index) {
+                Node::Resource(r) => V1Node::Resource(r.clone()),
+                Node::Task(t) => V1Node::Task(t.tasks.clone()),
+                Node::Alias(_) => unreachable!(),
+            });
+        }
+
+        for index in graph.edges() {
+            let ((a, b), weight) = graph.edge_from_index(index);
+            old.add_edge(a, b, *weight);
+        }
+
+        old
+    }
+
+    #[test]
+    fn test_migrate_v1() {
+        let graph = graph();
+        let input = graph
+            .node_to_index(&Node::Resource(res::File::new("foo.c").into()))
+            .unwrap();
+        let output = graph
+            .node_to_index(&Node::Resource(res::File::new("foo").into()))
+            .unwrap();
+        let task = graph
+            .nodes()
+            .find(|&i| matches!(graph.node_from_index(i), Node::Task(_)))
+            .unwrap();
+
+        let sha = Sha256::from_reader(&b"int main() {}"[..]).unwrap();
+
+        let mut checksums = HashMap::new();
+        checksums.insert(input, OldResourceState::Checksum(sha.clone()));
+        checksums.insert(output, OldResourceState::Missing);
+
+        let mut data = Vec::new();
+        bincode::serialize_into(&mut data, "0.1.0").unwrap();
+        bincode::serialize_into(&mut data, &old_graph(&graph)).unwrap();
+        bincode::serialize_into(&mut data, &vec![input]).unwrap();
+        bincode::serialize_into(&mut data, &checksums).unwrap();
+
+        let state = BuildState::from_reader(&data[..]).unwrap();
+
+        assert_eq!(state.graph.node_count(), graph.node_count());
+        assert_eq!(state.queue, vec![input]);
+        assert_eq!(
+            state.checksums.get(&input),
+            Some(&ResourceState::Checksum(res::Checksum::Sha256(sha)))
+        );
+        assert_eq!(state.checksums.get(&output), Some(&ResourceState::Missing));
+
+        // Tasks don't have any tags after the upgrade.
+        let tasks = graph.node_from_index(task).as_task().clone();
+        assert_eq!(
+            state.graph.node_from_index(task),
+            &Node::Task(TaskNode::new(tasks))
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let state = BuildState::from_graph(graph());
+
+        let mut data = Vec::new();
+        state.write_to(&mut data).unwrap();
+
+        let loaded = BuildState::from_reader(&data[..]).unwrap();
+        assert_eq!(loaded.graph.node_count(), state.graph.node_count());
+        assert_eq!(loaded.queue, state.queue);
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut data = Vec::new();
+        bincode::serialize_into(&mut data, "button-state").unwrap();
+        bincode::serialize_into(&mut data, &(CURRENT + 1)).unwrap();
+
+        let err = BuildState::from_reader(&data[..]).err().unwrap();
+        match err.downcast_ref::<StateError>() {
+            Some(StateError::UnsupportedVersion(v)) => {
+                assert_eq!(*v, CURRENT + 1)
+            }
+            _ => panic!("unexpected error: {}", err),
+        }
+
+        let mut data = Vec::new();
+        bincode::serialize_into(&mut data, "0.0.1").unwrap();
+
+        let err = BuildState::from_reader(&data[..]).err().unwrap();
+        assert!(err.downcast_ref::<StateError>().is_some());
+    }
+}
@@ -0,0 +1,423 @@
+// Copyright (c) 2018 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+pub(crate) mod journal;
+mod json;
+mod migrate;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::build_graph::{BuildGraph, Node};
+use crate::error::{BuildError, Error, ErrorKind, Fail, ResultExt};
+use crate::graph::{Algo, Diff, Indexable, Neighbors, NodeIndex, Nodes};
+use crate::res::ResourceState;
+use crate::util::Sha256;
+
+use bincode;
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+
+/// Identifies a file as a build state. This is followed by the schema version.
+const MAGIC: &str = "button-state";
+
+/// An error that can occur when reading the build state.
+#[derive(Fail, Display, Debug)]
+pub enum StateError {
+    /// The state was written by an unknown version of button.
+    #[display(fmt = "Unrecognized build state version '{}'", _0)]
+    UnknownVersion(String),
+
+    /// The state is truncated or otherwise damaged.
+    #[display(fmt = "Build state is corrupt: {}", _0)]
+    Corrupt(String),
+
+    /// There is no migration path from this schema version.
+    #[display(
+        fmt = "Build state schema version {} is not supported (the latest is \
+               {}). Run `button clean` with the version of button that \
+               created it or delete it manually.",
+        _0,
+        "migrate::CURRENT"
+    )]
+    UnsupportedVersion(u32),
+
+    /// More than one node has the same ID.
+    #[display(fmt = "Node ID {} is used more than once", _0)]
+    DuplicateId(usize),
+
+    /// The same node appears more than once.
+    #[display(fmt = "Node {} is a duplicate of another node", _0)]
+    DuplicateNode(usize),
+
+    /// A node ID was referenced that doesn't exist.
+    #[display(fmt = "Node ID {} does not exist", _0)]
+    UnknownId(usize),
+
+    /// Only resources can have a checksum.
+    #[display(fmt = "Task node {} cannot have a checksum", _0)]
+    TaskChecksum(usize),
+
+    /// An edge between two resources or two tasks.
+    #[display(
+        fmt = "Edge from {} to {} must be between a resource and a task",
+        _0,
+        _1
+    )]
+    InvalidEdge(usize, usize),
+}
+
+impl StateError {
+    fn corrupt<E: fmt::Display>(error: E) -> Error {
+        StateError::Corrupt(error.to_string()).into()
+    }
+}
+
+/// Returns `true` if the error (or one of its causes) is due to the build state
+/// being corrupt. A corrupt state can be recovered from.
+pub fn is_corrupt(error: &dyn Fail) -> bool {
+    error.iter_chain().any(|cause| {
+        matches!(cause.downcast_ref(), Some(StateError::Corrupt(_)))
+    })
+}
+
+/// Returns the path to the backup of the state at the given path. The backup
+/// is the previous version of the state.
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".bak");
+    PathBuf::from(path)
+}
+
+/// Copies the state at `path` to `backup`, if it exists. Like the state itself,
+/// the backup is copied to a temporary file first and then renamed into place.
+/// Thus, an interrupted copy can't leave a truncated backup behind.
+fn write_backup(path: &Path, dir: &Path, backup: &Path) -> Result<(), Error> {
+    let mut f = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+            return Ok(());
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut tempfile = NamedTempFile::new_in(dir)?;
+    io::copy(&mut f, &mut tempfile)?;
+    tempfile.persist(backup)?;
+
+    Ok(())
+}
+
+/// Reads the rest of the stream and verifies the SHA-256 trailer at the end of
+/// it. Returns the data before the trailer.
+fn read_checked<R: io::Read>(mut reader: R) -> Result<Vec<u8>, Error> {
+    const TRAILER_LEN: usize = 32;
+
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+
+    if data.len() < TRAILER_LEN {
+        return Err(StateError::corrupt("missing checksum"));
+    }
+
+    let trailer = data.split_off(data.len() - TRAILER_LEN);
+
+    if Sha256::from_reader(&data[..])?.as_bytes() != &trailer[..] {
+        return Err(StateError::corrupt("checksum mismatch"));
+    }
+
+    Ok(data)
+}
+
+/// The state of the build.
+#[derive(Serialize, Deserialize, Default)]
+pub struct BuildState {
+    /// The build graph.
+    pub graph: BuildGraph,
+
+    /// A persistent queue of node indices that should be visited. Duplicate
+    /// nodes don't matter here since nodes are only ever visited once when
+    /// traversing the graph.
+    pub queue: Vec<NodeIndex>,
+
+    /// Resource state. This is used to detect changes to resources. If the
+    /// resource does not exist in this map, then we don't yet know anything
+    /// about this resource and it should not be considered "owned" by the
+    /// build system. That is, the build system should never delete it if
+    /// it doesn't "own" it.
+    pub checksums: HashMap<NodeIndex, ResourceState>,
+}
+
+impl BuildState {
+    /// Constructs a state from a new build graph. Used when an existing state
+    /// does not exist on disk.
+    pub fn from_graph(graph: BuildGraph) -> BuildState {
+        // Everything needs to get built, so add all root nodes to the queue.
+        let queue = graph.root_nodes().collect();
+
+        BuildState {
+            graph,
+            queue,
+            checksums: HashMap::new(),
+        }
+    }
+
+    /// Constructs a state from a new build graph, assuming that the build
+    /// system owns every output. Used when the existing state has been lost.
+    /// Outputs that already exist are then rebuilt and can be deleted rather
+    /// than being left behind.
+    pub fn from_graph_owned(graph: BuildGraph) -> BuildState {
+        let mut state = BuildState::from_graph(graph);
+
+        for index in state.graph.nodes() {
+            if let Node::Resource(_) = state.graph.node_from_index(index) {
+                if !state.graph.is_root_node(index) {
+                    state.checksums.insert(index, ResourceState::Missing);
+                }
+            }
+        }
+
+        state
+    }
+
+    /// Reads the backup of the state at the given path.
+    pub fn from_backup<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<BuildState, BuildError> {
+        Self::from_path(backup_path(path.as_ref()))
+    }
+
+    /// Reads the state from a file.
+    pub fn from_path<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<BuildState, BuildError> {
+        let path = path.as_ref();
+        let f = fs::File::open(path)
+            .with_context(|_| ErrorKind::LoadState(path.to_path_buf()))?;
+        Ok(Self::from_reader(io::BufReader::new(f))
+            .with_context(|_| ErrorKind::LoadState(path.to_path_buf()))?)
+    }
+
+    /// Reads the state from a stream. States written with an older schema
+    /// version are migrated to the current version.
+    pub fn from_reader<R: io::Read>(
+        mut reader: R,
+    ) -> Result<BuildState, Error> {
+        // Read the header. Older states only have a version string here.
+        let magic: String = bincode::deserialize_from(&mut reader)
+            .map_err(StateError::corrupt)?;
+
+        let version = if magic == MAGIC {
+            bincode::deserialize_from(&mut reader)
+                .map_err(StateError::corrupt)?
+        } else {
+            migrate::legacy_version(&magic)
+                .ok_or(StateError::UnknownVersion(magic))?
+        };
+
+        if version == migrate::CURRENT {
+            let data = read_checked(reader)?;
+            Ok(bincode::deserialize(&data).map_err(StateError::corrupt)?)
+        } else {
+            migrate::migrate(version, reader).unwrap_or_else(|| {
+                Err(StateError::UnsupportedVersion(version).into())
+            })
+        }
+    }
+
+    /// Writes the state to a stream. The data is followed by its SHA-256 so
+    /// that corruption can be detected when reading it back.
+    fn write_to<W: io::Write>(
+        &self,
+        mut writer: W,
+    ) -> Result<(), bincode::Error> {
+        bincode::serialize_into(&mut writer, MAGIC)?;
+        bincode::serialize_into(&mut writer, &migrate::CURRENT)?;
+
+        let data = bincode::serialize(&self)?;
+        writer.write_all(&data)?;
+        writer.write_all(Sha256::from_reader(&data[..])?.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Writes the state to a file. The file is atomically updated using
+    /// a temporary file. Since the new state includes all changes recorded in
+    /// the journal, the journal is deleted afterwards.
+    ///
+    /// The previous state, if any, is kept as a backup in case this one gets
+    /// corrupted.
+    pub fn write_to_path<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<(), BuildError> {
+        let path = path.as_ref();
+
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+        let mut tempfile = NamedTempFile::new_in(dir)
+            .with_context(|_| {
+                format!("Failed creating temporary file in '{}'", dir.display())
+            })
+            .with_context(|_| ErrorKind::SaveState(path.to_path_buf()))?;
+
+        self.write_to(io::BufWriter::new(&mut tempfile))
+            .with_context(|_| ErrorKind::SaveState(path.to_path_buf()))?;
+
+        let backup = backup_path(path);
+
+        write_backup(path, dir, &backup)
+            .with_context(|_| {
+                format!(
+                    "Failed backing up build state to '{}'",
+                    backup.display()
+                )
+            })
+            .with_context(|_| ErrorKind::SaveState(path.to_path_buf()))?;
+
+        tempfile
+            .persist(path)
+            .with_context(|err| {
+                format!(
+                    "Failed persisting temporary file '{}' to '{}'",
+                    err.file.path().display(),
+                    path.display()
+                )
+            })
+            .with_context(|_| ErrorKind::SaveState(path.to_path_buf()))?;
+
+        journal::remove(path)
+            .context("Failed deleting build state journal")
+            .with_context(|_| ErrorKind::SaveState(path.to_path_buf()))?;
+
+        Ok(())
+    }
+
+    /// Performs a non-destructive (no file system changes) synchronization of
+    /// the state based on the new explicit graph.
+    ///
+    /// Deletion of removed output resources should be done before this.
+    pub fn update(&mut self, graph: &BuildGraph, diff: &Diff) {
+        // Remove edges before removing nodes so that the node removal has less
+        // work to do. (If a node has fewer neighbors, it has fewer edges to
+        // remove.)
+        for index in diff.left_only_edges.iter() {
+            assert!(self.graph.remove_edge(index).is_some());
+        }
+
+        // Remove nodes from the graph. This may invalidate the queue if the
+        // queue contains any of the nodes being removed here. Thus, we need to
+        // fix the queue after this removal.
+        for index in diff.left_only_nodes.iter() {
+            assert!(self.graph.remove_node(index).is_some());
+
+            // Fix the checksums.
+            self.checksums.remove(&index);
+        }
+
+        // Rebuild the queue with invalid indices filtered out.
+        let mut queue: Vec<_> = self
+            .queue
+            .iter()
+            .cloned()
+            .filter(|&index| self.graph.contains_node_index(index))
+            .collect();
+
+        for index in diff.right_only_nodes.iter() {
+            // New nodes should always be added to the queue such that they get
+            // traversed.
+            let node = graph.node_from_index(index);
+            let index = self.graph.add_node(node.clone());
+            queue.push(index);
+        }
+
+        for index in diff.right_only_edges.iter() {
+            let ((a, b), weight) = graph.edge_from_index(index);
+
+            // unwrapping because these nodes are guaranteed to exist in the
+            // graph at this point already.
+            let a = self.graph.node_to_index(graph.node_from_index(a)).unwrap();
+            let b = self.graph.node_to_index(graph.node_from_index(b)).unwrap();
+
+            self.graph.add_edge(a, b, *weight);
+        }
+
+        self.queue = queue;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::tempdir;
+
+    use crate::build_graph::FromRules;
+    use crate::graph::GraphBase;
+    use crate::rules::Rules;
+
+    #[test]
+    fn test_corrupt() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("state");
+
+        let rules = Rules::from_str(
+            r#"[{
+                "inputs": [{"file": "foo.c"}],
+                "tasks": [{"command": {"program": "gcc", "args": ["foo.c"]}}],
+                "outputs": [{"file": "foo"}]
+            }]"#,
+        )
+        .unwrap();
+
+        let graph = BuildGraph::from_rules(rules).unwrap();
+        let state = BuildState::from_graph_owned(graph);
+
+        // The output should be owned.
+        assert_eq!(state.checksums.len(), 1);
+
+        // Write it twice so that there is a backup.
+        state.write_to_path(&path).unwrap();
+        state.write_to_path(&path).unwrap();
+
+        // Flip a bit somewhere in the middle.
+        let mut data = fs::read(&path).unwrap();
+        let middle = data.len() / 2;
+        data[middle] ^= 1;
+        fs::write(&path, &data).unwrap();
+
+        let err = BuildState::from_path(&path).err().unwrap();
+        assert!(is_corrupt(&err));
+
+        // Truncate it.
+        fs::write(&path, &data[..middle]).unwrap();
+
+        let err = BuildState::from_path(&path).err().unwrap();
+        assert!(is_corrupt(&err));
+
+        let backup = BuildState::from_backup(&path).unwrap();
+        assert_eq!(backup.graph.node_count(), state.graph.node_count());
+        assert_eq!(backup.checksums, state.checksums);
+    }
+}
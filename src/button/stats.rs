@@ -0,0 +1,275 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Historical build statistics.
+//!
+//! The durations of tasks and a summary of each build are recorded across
+//! builds. This is used to estimate how long a build will take and to find the
+//! tasks that are slow or get rebuilt often.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use bincode;
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+
+use crate::error::{Error, ResultExt};
+use crate::events::Timestamp;
+
+/// Number of durations to remember for each task.
+const DURATIONS: usize = 10;
+
+/// Number of builds to remember.
+const BUILDS: usize = 50;
+
+/// Statistics for a single task.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TaskStats {
+    /// Number of times the task has been executed.
+    pub runs: u64,
+
+    /// Number of times the task has failed.
+    pub failures: u64,
+
+    /// The most recent durations of the task, oldest first.
+    pub durations: VecDeque<Duration>,
+
+    /// When the task was last executed.
+    pub last_run: Option<Timestamp>,
+}
+
+impl TaskStats {
+    /// The average of the most recent durations.
+    pub fn average(&self) -> Option<Duration> {
+        if self.durations.is_empty() {
+            return None;
+        }
+
+        let total: Duration = self.durations.iter().sum();
+        Some(total / self.durations.len() as u32)
+    }
+
+    /// The most recent duration.
+    pub fn last(&self) -> Option<Duration> {
+        self.durations.back().cloned()
+    }
+}
+
+/// Statistics for a single build.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BuildStats {
+    /// The name of the build.
+    pub name: String,
+
+    /// When the build started.
+    pub timestamp: Timestamp,
+
+    /// How long the build took.
+    pub duration: Duration,
+
+    /// Number of tasks that were executed.
+    pub executed: usize,
+
+    /// Number of tasks that didn't need to be executed because they were
+    /// already up-to-date.
+    pub cached: usize,
+
+    /// Number of tasks that failed.
+    pub failed: usize,
+
+    /// `true` if the build succeeded.
+    pub success: bool,
+}
+
+/// A history of build statistics.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Stats {
+    builds: VecDeque<BuildStats>,
+    tasks: HashMap<String, TaskStats>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the statistics from a file. If the file doesn't exist, empty
+    /// statistics are returned.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Stats, Error> {
+        let path = path.as_ref();
+
+        let f = match fs::File::open(path) {
+            Ok(f) => f,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(Stats::new());
+            }
+            Err(err) => {
+                return Err(err).with_context(|_| {
+                    format!("Failed opening '{}'", path.display())
+                })?;
+            }
+        };
+
+        Ok(bincode::deserialize_from(io::BufReader::new(f))
+            .with_context(|_| format!("Failed loading '{}'", path.display()))?)
+    }
+
+    /// Writes the statistics to a file atomically.
+    pub fn write_to_path<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+        let mut tempfile = NamedTempFile::new_in(dir).with_context(|_| {
+            format!("Failed creating temporary file in '{}'", dir.display())
+        })?;
+
+        bincode::serialize_into(io::BufWriter::new(&mut tempfile), self)
+            .with_context(|_| format!("Failed writing '{}'", path.display()))?;
+
+        tempfile.persist(path).with_context(|err| {
+            format!(
+                "Failed persisting temporary file '{}' to '{}'",
+                err.file.path().display(),
+                path.display()
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// Records a task execution.
+    pub fn add_task(
+        &mut self,
+        name: String,
+        timestamp: Timestamp,
+        duration: Duration,
+        success: bool,
+    ) {
+        let task = self.tasks.entry(name).or_default();
+
+        task.runs += 1;
+
+        if !success {
+            task.failures += 1;
+        }
+
+        if task.durations.len() >= DURATIONS {
+            task.durations.pop_front();
+        }
+
+        task.durations.push_back(duration);
+        task.last_run = Some(timestamp);
+    }
+
+    /// Records a build.
+    pub fn add_build(&mut self, build: BuildStats) {
+        if self.builds.len() >= BUILDS {
+            self.builds.pop_front();
+        }
+
+        self.builds.push_back(build);
+    }
+
+    /// Returns the statistics for a task.
+    pub fn task(&self, name: &str) -> Option<&TaskStats> {
+        self.tasks.get(name)
+    }
+
+    /// Returns an iterator over the statistics of all tasks.
+    pub fn tasks(&self) -> impl Iterator<Item = (&str, &TaskStats)> {
+        self.tasks.iter().map(|(name, task)| (name.as_str(), task))
+    }
+
+    /// Returns an iterator over the recorded builds, oldest first.
+    pub fn builds(&self) -> impl Iterator<Item = &BuildStats> {
+        self.builds.iter()
+    }
+
+    /// Estimates how long a task will take to execute based on its previous
+    /// durations.
+    pub fn estimate(&self, name: &str) -> Option<Duration> {
+        self.task(name).and_then(TaskStats::average)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_estimate() {
+        let mut stats = Stats::new();
+
+        assert_eq!(stats.estimate("a"), None);
+
+        for secs in 1..=(DURATIONS as u64 + 2) {
+            stats.add_task(
+                "a".into(),
+                Utc::now(),
+                Duration::from_secs(secs),
+                secs != 1,
+            );
+        }
+
+        let task = stats.task("a").unwrap();
+        assert_eq!(task.runs, DURATIONS as u64 + 2);
+        assert_eq!(task.failures, 1);
+        assert_eq!(task.durations.len(), DURATIONS);
+        assert_eq!(task.last(), Some(Duration::from_secs(12)));
+
+        // Only the last 10 durations (3..=12) are used.
+        assert_eq!(stats.estimate("a"), Some(Duration::from_millis(7500)));
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), Error> {
+        let tempdir = TempDir::new()?;
+        let path = tempdir.path().join("stats");
+
+        assert!(Stats::from_path(&path)?.builds().next().is_none());
+
+        let mut stats = Stats::new();
+        stats.add_task("a".into(), Utc::now(), Duration::from_secs(1), true);
+        stats.add_build(BuildStats {
+            name: "build".into(),
+            timestamp: Utc::now(),
+            duration: Duration::from_secs(2),
+            executed: 1,
+            cached: 3,
+            failed: 0,
+            success: true,
+        });
+        stats.write_to_path(&path)?;
+
+        let stats = Stats::from_path(&path)?;
+        assert_eq!(stats.builds().count(), 1);
+        assert_eq!(stats.estimate("a"), Some(Duration::from_secs(1)));
+
+        Ok(())
+    }
+}
@@ -19,7 +19,6 @@
 // THE SOFTWARE.
 
 use std::fmt;
-use std::io;
 use std::path::Path;
 
 use derive_more::{Display, From};
@@ -32,7 +31,7 @@ use super::download::Download;
 use super::makedir::MakeDir;
 
 use super::traits::Task;
-use crate::detect::Detected;
+use crate::detect::{Detected, Log};
 use crate::error::Error;
 
 use crate::res;
@@ -90,7 +89,7 @@ impl Task for Any {
     fn execute(
         &self,
         root: &Path,
-        log: &mut dyn io::Write,
+        log: &mut dyn Log,
     ) -> Result<Detected, Error> {
         match self {
             Any::BatchScript(ref x) => x.execute(root, log),
@@ -120,4 +119,14 @@ impl Task for Any {
             Any::Copy(ref x) => x.known_outputs(set),
         }
     }
+
+    fn shell_command(&self) -> Option<String> {
+        match self {
+            Any::BatchScript(ref x) => x.shell_command(),
+            Any::Command(ref x) => x.shell_command(),
+            Any::Download(ref x) => x.shell_command(),
+            Any::MakeDir(ref x) => x.shell_command(),
+            Any::Copy(ref x) => x.shell_command(),
+        }
+    }
 }
@@ -20,8 +20,8 @@
 
 use std::collections::BTreeMap;
 use std::fmt;
+use std::io::Read;
 use std::io::Write as IoWrite;
-use std::io::{self, Read};
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
@@ -30,7 +30,7 @@ use tempfile;
 use crate::error::Error;
 
 use super::traits::Task;
-use crate::detect::Detected;
+use crate::detect::{Detected, Log};
 
 use crate::util::{progress_dummy, Arguments, Process, Retry};
 
@@ -65,7 +65,7 @@ impl BatchScript {
     fn execute_impl(
         &self,
         root: &Path,
-        log: &mut dyn io::Write,
+        log: &mut dyn Log,
     ) -> Result<Detected, Error> {
         // Write the script contents to a temporary file for execution. This
         // temporary file must outlive the spawned process.
@@ -128,7 +128,7 @@ impl Task for BatchScript {
     fn execute(
         &self,
         root: &Path,
-        log: &mut dyn io::Write,
+        log: &mut dyn Log,
     ) -> Result<Detected, Error> {
         if let Some(ref retry) = self.retry {
             retry.call(|| self.execute_impl(root, log), progress_dummy)
@@ -19,7 +19,6 @@
 // THE SOFTWARE.
 
 use std::fmt;
-use std::io;
 use std::path::{Path, PathBuf};
 
 use serde::{
@@ -30,10 +29,10 @@ use serde::{
 
 use crate::error::Error;
 use crate::res;
-use crate::util::{progress_dummy, Arguments, Process, Retry};
+use crate::util::{progress_dummy, Arg, Arguments, Process, Retry};
 
 use super::traits::Task;
-use crate::detect::{Detect, Detected};
+use crate::detect::{Detect, Detected, Log};
 
 const DEV_NULL: &str = "/dev/null";
 
@@ -71,16 +70,29 @@ impl Command {
         self
     }
 
+    /// Sets the input and output detection method.
+    pub(crate) fn with_detect(mut self, detect: Detect) -> Self {
+        self.detect = Some(detect);
+        self
+    }
+
+    /// The process that gets spawned by this command.
+    pub fn process(&self) -> &Process {
+        &self.process
+    }
+
+    /// The detection method used for this command.
+    pub(crate) fn detect(&self) -> Detect {
+        self.detect
+            .unwrap_or_else(|| Detect::from_program(&self.process.program))
+    }
+
     fn execute_impl(
         &self,
         root: &Path,
-        log: &mut dyn io::Write,
+        log: &mut dyn Log,
     ) -> Result<Detected, Error> {
-        let detect = self
-            .detect
-            .unwrap_or_else(|| Detect::from_program(&self.process.program));
-
-        let detected = detect.run(root, &self.process, log)?;
+        let detected = self.detect().run(root, &self.process, log)?;
 
         Ok(detected)
     }
@@ -106,7 +118,7 @@ impl Task for Command {
     fn execute(
         &self,
         root: &Path,
-        log: &mut dyn io::Write,
+        log: &mut dyn Log,
     ) -> Result<Detected, Error> {
         if let Some(retry) = &self.retry {
             retry.call(|| self.execute_impl(root, log), progress_dummy)
@@ -166,6 +178,45 @@ impl Task for Command {
             }
         }
     }
+
+    fn shell_command(&self) -> Option<String> {
+        let process = &self.process;
+        let path = |p: &Path| Arg::new(&p.to_string_lossy()).to_string();
+
+        let mut command = String::new();
+
+        if let Some(cwd) = &process.cwd {
+            command.push_str(&format!("cd {} && ", path(cwd)));
+        }
+
+        if let Some(env) = &process.env {
+            command.push_str("env ");
+            for (name, value) in env {
+                let var = format!("{}={}", name, value);
+                command.push_str(&format!("{} ", Arg::new(&var)));
+            }
+        }
+
+        command.push_str(&path(&process.program));
+
+        for arg in &process.args {
+            command.push_str(&format!(" {}", arg));
+        }
+
+        if let Some(stdin) = &process.stdin {
+            command.push_str(&format!(" < {}", path(stdin)));
+        }
+
+        if let Some(stdout) = &process.stdout {
+            command.push_str(&format!(" > {}", path(stdout)));
+        }
+
+        if let Some(stderr) = &process.stderr {
+            command.push_str(&format!(" 2> {}", path(stderr)));
+        }
+
+        Some(command)
+    }
 }
 
 // Implement Serialize manually because `#[serde(flatten)]` doesn't work with
@@ -20,15 +20,14 @@
 
 use std::fmt;
 use std::fs;
-use std::io;
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
-use crate::detect::Detected;
+use crate::detect::{Detected, Log};
 use crate::error::Error;
 use crate::res;
-use crate::util::{progress_dummy, Retry};
+use crate::util::{progress_dummy, Arg, Retry};
 
 use super::traits::Task;
 
@@ -51,7 +50,7 @@ impl Copy {
     fn execute_impl(
         &self,
         root: &Path,
-        _log: &mut dyn io::Write,
+        _log: &mut dyn Log,
     ) -> Result<Detected, Error> {
         fs::copy(&root.join(&self.from), &root.join(&self.to))?;
         Ok(Detected::new())
@@ -79,7 +78,7 @@ impl Task for Copy {
     fn execute(
         &self,
         root: &Path,
-        log: &mut dyn io::Write,
+        log: &mut dyn Log,
     ) -> Result<Detected, Error> {
         if let Some(retry) = &self.retry {
             retry.call(|| self.execute_impl(root, log), progress_dummy)
@@ -103,4 +102,12 @@ impl Task for Copy {
     fn known_outputs(&self, set: &mut res::Set) {
         set.insert(self.to.clone().into());
     }
+
+    fn shell_command(&self) -> Option<String> {
+        Some(format!(
+            "cp {} {}",
+            Arg::new(&self.from.to_string_lossy()),
+            Arg::new(&self.to.to_string_lossy())
+        ))
+    }
 }
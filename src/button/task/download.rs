@@ -27,10 +27,10 @@ use reqwest;
 use serde::{Deserialize, Serialize};
 use tempfile::NamedTempFile;
 
-use crate::detect::Detected;
+use crate::detect::{Detected, Log};
 use crate::error::{Error, ResultExt};
 use crate::res;
-use crate::util::{progress_dummy, Retry, Sha256, ShaVerifyError};
+use crate::util::{progress_dummy, Arg, Retry, Sha256, ShaVerifyError};
 
 use super::traits::Task;
 
@@ -74,7 +74,7 @@ impl Task for Download {
     fn execute(
         &self,
         root: &Path,
-        log: &mut dyn io::Write,
+        log: &mut dyn Log,
     ) -> Result<Detected, Error> {
         let path = root.join(&self.path);
 
@@ -114,4 +114,21 @@ impl Task for Download {
         // TODO: Depend on output directory.
         resources.insert(self.path.clone().into());
     }
+
+    fn shell_command(&self) -> Option<String> {
+        let path = self.path.to_string_lossy();
+
+        let timeout = match self.timeout {
+            Some(timeout) => format!("--max-time {} ", timeout.as_secs()),
+            None => String::new(),
+        };
+
+        Some(format!(
+            "curl -fsSL {}-o {} {} && echo {} | sha256sum --quiet -c -",
+            timeout,
+            Arg::new(&path),
+            Arg::new(&self.url),
+            Arg::new(&format!("{}  {}", self.sha256, path)),
+        ))
+    }
 }
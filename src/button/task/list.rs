@@ -19,7 +19,6 @@
 // THE SOFTWARE.
 
 use std::fmt;
-use std::io;
 use std::ops;
 use std::path::Path;
 
@@ -27,7 +26,7 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use super::any::Any;
 use super::traits::Task;
-use crate::detect::Detected;
+use crate::detect::{Detected, Log};
 use crate::error::Error;
 
 use crate::res;
@@ -99,7 +98,7 @@ impl Task for List {
     fn execute(
         &self,
         root: &Path,
-        log: &mut dyn io::Write,
+        log: &mut dyn Log,
     ) -> Result<Detected, Error> {
         let mut detected = Detected::new();
 
@@ -122,4 +121,14 @@ impl Task for List {
             task.known_outputs(resources);
         }
     }
+
+    fn shell_command(&self) -> Option<String> {
+        let commands = self
+            .list
+            .iter()
+            .map(Task::shell_command)
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(commands.join(" && "))
+    }
 }
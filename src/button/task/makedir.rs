@@ -25,10 +25,10 @@ use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
-use crate::detect::Detected;
+use crate::detect::{Detected, Log};
 use crate::error::Error;
 use crate::res;
-use crate::util::{progress_dummy, Retry};
+use crate::util::{progress_dummy, Arg, Retry};
 
 use super::traits::Task;
 
@@ -52,7 +52,7 @@ impl MakeDir {
     fn execute_impl(
         &self,
         root: &Path,
-        _log: &mut dyn io::Write,
+        _log: &mut dyn Log,
     ) -> Result<Detected, Error> {
         // Only create the last directory, not the entire directory path. We
         // would not be able to properly clean up directories if we did the
@@ -91,7 +91,7 @@ impl Task for MakeDir {
     fn execute(
         &self,
         root: &Path,
-        log: &mut dyn io::Write,
+        log: &mut dyn Log,
     ) -> Result<Detected, Error> {
         if let Some(retry) = &self.retry {
             retry.call(|| self.execute_impl(root, log), progress_dummy)
@@ -114,6 +114,14 @@ impl Task for MakeDir {
     fn known_outputs(&self, set: &mut res::Set) {
         set.insert(res::Dir::new(self.path.clone()).into());
     }
+
+    fn shell_command(&self) -> Option<String> {
+        // `-p` so that it doesn't fail if the directory already exists.
+        Some(format!(
+            "mkdir -p {}",
+            Arg::new(&self.path.to_string_lossy())
+        ))
+    }
 }
 
 #[cfg(test)]
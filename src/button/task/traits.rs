@@ -20,10 +20,9 @@
 
 use std::fmt;
 use std::hash::Hash;
-use std::io;
 use std::path::Path;
 
-use crate::detect::Detected;
+use crate::detect::{Detected, Log};
 use crate::error::Error;
 use serde::Serialize;
 
@@ -49,7 +48,7 @@ pub trait Task:
     fn execute(
         &self,
         root: &Path,
-        log: &mut dyn io::Write,
+        log: &mut dyn Log,
     ) -> Result<Detected, Error>;
 
     /// Inputs the task knows about *a priori*. It must calculate these by
@@ -66,4 +65,11 @@ pub trait Task:
     /// *only* looking at the task parameters. It cannot do anything fancy like
     /// running an external process to determine these.
     fn known_outputs(&self, _resources: &mut res::Set) {}
+
+    /// An equivalent shell command. This is used to export the build to other
+    /// build systems. Returns `None` if the task can't be expressed as a shell
+    /// command.
+    fn shell_command(&self) -> Option<String> {
+        None
+    }
 }
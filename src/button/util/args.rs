@@ -19,9 +19,11 @@
 // THE SOFTWARE.
 use std::ffi::OsStr;
 use std::fmt::{self, Write as FmtWrite};
+use std::fs;
 use std::io::{self, Write};
 use std::iter;
 use std::ops;
+use std::path::Path;
 
 use serde::{Deserialize, Serialize};
 use tempfile::{NamedTempFile, TempPath};
@@ -289,6 +291,102 @@ impl Arguments {
         Ok(tempfile.into_temp_path())
     }
 
+    /// Parses the contents of a response file. This is the inverse of
+    /// `response_file`.
+    pub fn from_response_file(contents: &str) -> Arguments {
+        let contents = contents.trim_start_matches('\u{feff}');
+
+        let mut args = Arguments::new();
+        let mut arg = String::new();
+        let mut in_arg = false;
+        let mut quote = None;
+        let mut chars = contents.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match (c, quote) {
+                ('\\', q) if q != Some('\'') => {
+                    match chars.peek() {
+                        // Windows paths use backslashes as separators. Only
+                        // quotes and backslashes can be escaped there.
+                        #[cfg(windows)]
+                        Some(&next) if next == '"' || next == '\\' => {
+                            arg.push(next);
+                            chars.next();
+                        }
+                        #[cfg(windows)]
+                        _ => arg.push(c),
+
+                        #[cfg(unix)]
+                        Some(&next) => {
+                            arg.push(next);
+                            chars.next();
+                        }
+                        #[cfg(unix)]
+                        None => arg.push(c),
+                    }
+                    in_arg = true;
+                }
+                (c, Some(q)) if c == q => quote = None,
+                (c, Some(_)) => arg.push(c),
+                ('"', None) | ('\'', None) => {
+                    quote = Some(c);
+                    in_arg = true;
+                }
+                (c, None) if c.is_whitespace() => {
+                    if in_arg {
+                        args.push(ArgBuf::from(std::mem::take(&mut arg)));
+                        in_arg = false;
+                    }
+                }
+                (c, None) => {
+                    arg.push(c);
+                    in_arg = true;
+                }
+            }
+        }
+
+        if in_arg {
+            args.push(ArgBuf::from(arg));
+        }
+
+        args
+    }
+
+    /// Replaces `@file` arguments with the contents of the response file.
+    /// Relative paths are relative to `dir`, which should be the working
+    /// directory of the process. Response files can include other response
+    /// files.
+    pub fn expand_response_files(&self, dir: &Path) -> io::Result<Arguments> {
+        self.expand_response_files_impl(dir, 0)
+    }
+
+    fn expand_response_files_impl(
+        &self,
+        dir: &Path,
+        depth: usize,
+    ) -> io::Result<Arguments> {
+        // Guard against response files that include themselves.
+        const MAX_DEPTH: usize = 16;
+
+        let mut args = Arguments::new();
+
+        for arg in self.iter() {
+            match arg.strip_prefix('@') {
+                Some(path) if depth < MAX_DEPTH => {
+                    let contents = fs::read_to_string(dir.join(path))?;
+                    args.extend(
+                        Arguments::from_response_file(&contents)
+                            .expand_response_files_impl(dir, depth + 1)?
+                            .0,
+                    );
+                }
+                _ => args.push(arg.clone()),
+            }
+        }
+
+        Ok(args)
+    }
+
     /// Write a response file to an arbitrary writer.
     fn write_response_file(
         &self,
@@ -431,4 +529,66 @@ mod tests {
 
         assert_eq!(format!("{}", ArgBuf::from(r"foo bar")), "\"foo bar\"");
     }
+
+    #[test]
+    fn test_from_response_file() {
+        let args = Arguments::from_response_file(
+            "\u{feff}-c  foo.c\n-o \"foo bar.o\" 'it''s' -DX=\"\"\n",
+        );
+
+        assert_eq!(
+            args,
+            Arguments::from(vec![
+                ArgBuf::from("-c"),
+                ArgBuf::from("foo.c"),
+                ArgBuf::from("-o"),
+                ArgBuf::from("foo bar.o"),
+                ArgBuf::from("its"),
+                ArgBuf::from("-DX="),
+            ])
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_response_file_round_trip() {
+        let args = Arguments::from(vec![
+            ArgBuf::from("foo bar"),
+            ArgBuf::from("$HOME"),
+            ArgBuf::from("\"quoted\""),
+            ArgBuf::from(r"back\slash"),
+        ]);
+
+        let mut buf = Vec::new();
+        args.write_response_file(&mut buf).unwrap();
+
+        assert_eq!(
+            Arguments::from_response_file(std::str::from_utf8(&buf).unwrap()),
+            args
+        );
+    }
+
+    #[test]
+    fn test_expand_response_files() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("inner.rsp"), "-DINNER")?;
+        fs::write(dir.path().join("outer.rsp"), "-O2 @inner.rsp foo.c")?;
+
+        let args = Arguments::from(vec![
+            ArgBuf::from("-c"),
+            ArgBuf::from("@outer.rsp"),
+        ]);
+
+        assert_eq!(
+            args.expand_response_files(dir.path())?,
+            Arguments::from(vec![
+                ArgBuf::from("-c"),
+                ArgBuf::from("-O2"),
+                ArgBuf::from("-DINNER"),
+                ArgBuf::from("foo.c"),
+            ])
+        );
+
+        Ok(())
+    }
 }
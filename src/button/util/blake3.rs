@@ -0,0 +1,51 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+use super::digest::{Algorithm, Digest};
+
+/// The BLAKE3 algorithm.
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum Blake3Algorithm {}
+
+impl Algorithm for Blake3Algorithm {
+    type Hasher = blake3::Hasher;
+
+    fn new() -> Self::Hasher {
+        blake3::Hasher::new()
+    }
+
+    fn update(hasher: &mut Self::Hasher, data: &[u8]) {
+        hasher.update(data);
+    }
+
+    fn finalize(hasher: Self::Hasher) -> [u8; 32] {
+        hasher.finalize().into()
+    }
+}
+
+/// Wrapper around a BLAKE3 hash value.
+///
+/// This can be serialized and deserialized as hex.
+pub type Blake3 = Digest<Blake3Algorithm>;
+
+impl From<blake3::Hash> for Blake3 {
+    fn from(hash: blake3::Hash) -> Self {
+        Blake3::from(<[u8; 32]>::from(hash))
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright (c) 2019 Jason White
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Hash values that can be serialized and deserialized as hex. This is shared
+//! by each of the supported hash algorithms.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::marker::PhantomData;
+use std::path::Path;
+
+use hex::{FromHex, FromHexError, ToHex};
+use serde::{
+    de::{self, Deserializer, Visitor},
+    ser::Serializer,
+    Deserialize, Serialize,
+};
+
+/// A hash algorithm with a 256-bit output.
+pub trait Algorithm {
+    type Hasher;
+
+    fn new() -> Self::Hasher;
+    fn update(hasher: &mut Self::Hasher, data: &[u8]);
+    fn finalize(hasher: Self::Hasher) -> [u8; 32];
+}
+
+/// Wrapper around a hash value computed by the algorithm `A`.
+///
+/// This can be serialized and deserialized as hex.
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Digest<A> {
+    inner: [u8; 32],
+    algorithm: PhantomData<A>,
+}
+
+impl<A> Default for Digest<A>
+where
+    A: Algorithm,
+{
+    fn default() -> Self {
+        Self::from(A::finalize(A::new()))
+    }
+}
+
+impl<A> From<[u8; 32]> for Digest<A> {
+    fn from(inner: [u8; 32]) -> Self {
+        Digest {
+            inner,
+            algorithm: PhantomData,
+        }
+    }
+}
+
+impl<A> Digest<A>
+where
+    A: Algorithm,
+{
+    pub fn from_reader<R>(mut reader: R) -> io::Result<Self>
+    where
+        R: io::Read,
+    {
+        let mut hasher = A::new();
+
+        const BUF_SIZE: usize = 16384;
+
+        let mut buf = [0u8; BUF_SIZE];
+
+        loop {
+            let n = reader.read(&mut buf)?;
+
+            if n == 0 {
+                break;
+            }
+
+            A::update(&mut hasher, &buf[0..n]);
+        }
+
+        Ok(Self::from(A::finalize(hasher)))
+    }
+
+    pub fn from_path<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_reader(fs::File::open(path.as_ref())?)
+    }
+}
+
+impl<A> Digest<A> {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.inner
+    }
+}
+
+impl<A> fmt::Display for Digest<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex: String = self.inner.encode_hex();
+        f.write_str(&hex)
+    }
+}
+
+impl<A> fmt::Debug for Digest<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl<A> Serialize for Digest<A> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            // Serialize as a hex string.
+            let hex: String = self.inner.encode_hex();
+            serializer.serialize_str(&hex)
+        } else {
+            // Serialize as a byte array with known length.
+            serializer.serialize_bytes(&self.inner)
+        }
+    }
+}
+
+impl<'de, A> Deserialize<'de> for Digest<A> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct HexVisitor<A>(PhantomData<A>);
+
+        impl<'de, A> Visitor<'de> for HexVisitor<A> {
+            type Value = Digest<A>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "hex string or bytes")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                let inner = <[u8; 32]>::from_hex(v).map_err(|e| match e {
+                    FromHexError::InvalidHexCharacter { c, .. } => {
+                        E::invalid_value(
+                            de::Unexpected::Char(c),
+                            &"string with only hexadecimal characters",
+                        )
+                    }
+                    FromHexError::InvalidStringLength => E::invalid_length(
+                        v.len(),
+                        &"hex string with a valid length",
+                    ),
+                    FromHexError::OddLength => E::invalid_length(
+                        v.len(),
+                        &"hex string with an even length",
+                    ),
+                })?;
+
+                Ok(Digest::from(inner))
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                if v.len() != 32 {
+                    return Err(E::invalid_length(v.len(), &"32 bytes"));
+                }
+
+                let mut inner = [0u8; 32];
+                inner.copy_from_slice(v);
+                Ok(Digest::from(inner))
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            // Deserialize from a hex string.
+            deserializer.deserialize_str(HexVisitor(PhantomData))
+        } else {
+            // Deserialize from a byte array with known length.
+            deserializer.deserialize_bytes(HexVisitor(PhantomData))
+        }
+    }
+}
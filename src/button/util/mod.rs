@@ -18,10 +18,13 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 // THE SOFTWARE.
 mod args;
+mod blake3;
 mod counter;
+mod digest;
 mod futures;
 mod iter;
 mod make;
+mod markup;
 mod path;
 mod proc;
 mod queue;
@@ -29,10 +32,12 @@ mod retry;
 mod sha256;
 
 pub use self::args::{Arg, ArgBuf, Arguments};
+pub use self::blake3::Blake3;
 pub use self::counter::Counter;
 pub use self::futures::Either;
 pub use self::iter::empty_or_any;
 pub use self::make::{MakeFile, MakeRule};
+pub use self::markup::escape_markup;
 pub use self::path::PathExt;
 pub use self::proc::{Child, Process};
 pub use self::queue::RandomQueue;